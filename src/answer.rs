@@ -1,5 +1,24 @@
 use crate::diff;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
 use regex::Regex;
+use std::sync::LazyLock;
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Theme, ThemeSet},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+static THEME: LazyLock<Theme> = LazyLock::new(|| {
+    ThemeSet::load_defaults()
+        .themes
+        .remove("base16-ocean.dark")
+        .unwrap_or_default()
+});
 
 pub fn format_answer(answer: &str) -> String {
     let trimmed = answer.trim();
@@ -30,13 +49,157 @@ pub fn format_answer(answer: &str) -> String {
         let diff = diff::unified_diff("", &code, &path);
         output.push_str(&diff);
         if idx + 1 < code_blocks.len() {
-            output.push_str("\n");
+            output.push('\n');
         }
     }
 
     output.trim_end().to_string()
 }
 
+/// Renders `answer` for the TUI, keeping prose and code in their original
+/// order. Fenced code is highlighted by its language; unknown languages are
+/// shown as plain text.
+pub fn highlight_answer(answer: &str, text_style: Style) -> Vec<Line<'static>> {
+    let trimmed = answer.trim();
+    let re = Regex::new(r"(?s)```([a-zA-Z0-9_+\-.]*)\n(.*?)```\s*").unwrap();
+    let mut lines = Vec::new();
+    let mut last_end = 0;
+    for caps in re.captures_iter(trimmed) {
+        let whole = caps.get(0).unwrap();
+        push_text(&mut lines, &trimmed[last_end..whole.start()], text_style);
+        let language = caps.get(1).map(|m| m.as_str()).unwrap_or_default();
+        let code = caps.get(2).map(|m| m.as_str()).unwrap_or_default();
+        lines.extend(highlight_code(language, code.trim_matches('\n')));
+        lines.push(Line::raw(""));
+        last_end = whole.end();
+    }
+    push_text(&mut lines, &trimmed[last_end..], text_style);
+    while lines.last().is_some_and(|l| l.width() == 0) {
+        lines.pop();
+    }
+    lines
+}
+
+fn push_text(lines: &mut Vec<Line<'static>>, text: &str, style: Style) {
+    let text = text.trim();
+    if text.is_empty() {
+        return;
+    }
+    lines.extend(text.lines().map(|l| markdown_line(l, style)));
+    lines.push(Line::raw(""));
+}
+
+/// Styles one line of prose: headings bold, list items indented, `code` and
+/// **bold** spans inline. Anything else passes through unchanged.
+fn markdown_line(line: &str, style: Style) -> Line<'static> {
+    let trimmed = line.trim_start();
+    let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+        let heading = style.add_modifier(Modifier::BOLD);
+        return Line::from(inline_spans(trimmed[hashes..].trim(), heading));
+    }
+
+    let depth = (line.len() - trimmed.len()) / 2;
+    let indent = "  ".repeat(depth + 1);
+    if let Some(item) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|bullet| trimmed.strip_prefix(bullet))
+    {
+        let mut spans = vec![Span::styled(format!("{indent}• "), style)];
+        spans.extend(inline_spans(item, style));
+        return Line::from(spans);
+    }
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && trimmed[digits..].starts_with(". ") {
+        let mut spans = vec![Span::styled(
+            format!("{indent}{} ", &trimmed[..digits + 1]),
+            style,
+        )];
+        spans.extend(inline_spans(&trimmed[digits + 2..], style));
+        return Line::from(spans);
+    }
+
+    Line::from(inline_spans(line, style))
+}
+
+fn inline_spans(text: &str, style: Style) -> Vec<Span<'static>> {
+    let code_style = Style::default().fg(Color::Yellow);
+    let mut spans = Vec::new();
+    for (idx, part) in text.split('`').enumerate() {
+        // An unmatched trailing backtick leaves an odd segment; show it as typed.
+        let is_code = idx % 2 == 1 && text.matches('`').count() > idx;
+        if is_code {
+            spans.push(Span::styled(part.to_string(), code_style));
+            continue;
+        }
+        let prefix = if idx % 2 == 1 { "`" } else { "" };
+        for (bold_idx, chunk) in format!("{prefix}{part}").split("**").enumerate() {
+            if chunk.is_empty() {
+                continue;
+            }
+            let chunk_style = if bold_idx % 2 == 1 {
+                style.add_modifier(Modifier::BOLD)
+            } else {
+                style
+            };
+            spans.push(Span::styled(chunk.to_string(), chunk_style));
+        }
+    }
+    spans
+}
+
+fn highlight_code(language: &str, code: &str) -> Vec<Line<'static>> {
+    let Some(syntax) = SYNTAXES.find_syntax_by_token(language) else {
+        let dim = Style::default().add_modifier(Modifier::DIM);
+        return code
+            .lines()
+            .map(|l| Line::styled(l.to_string(), dim))
+            .collect();
+    };
+    let mut highlighter = HighlightLines::new(syntax, &THEME);
+    LinesWithEndings::from(code)
+        .map(|line| match highlighter.highlight_line(line, &SYNTAXES) {
+            Ok(ranges) => Line::from(
+                ranges
+                    .into_iter()
+                    .map(|(style, text)| {
+                        let fg = style.foreground;
+                        Span::styled(
+                            text.trim_end_matches(['\n', '\r']).to_string(),
+                            Style::default().fg(Color::Rgb(fg.r, fg.g, fg.b)),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            Err(_) => Line::raw(line.trim_end_matches(['\n', '\r']).to_string()),
+        })
+        .collect()
+}
+
+/// Renders lines of a file for `/open`: highlighted by the file's extension
+/// and numbered from `first_line`.
+pub fn highlight_file(path: &str, code: &str, first_line: usize) -> Vec<Line<'static>> {
+    let ext = std::path::Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+    let last_line = first_line + code.lines().count().saturating_sub(1);
+    let width = last_line.to_string().len();
+    let gutter = Style::default().add_modifier(Modifier::DIM);
+    highlight_code(ext, code)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, line)| {
+            let mut spans = vec![Span::styled(
+                format!("{:>width$} │ ", first_line + idx),
+                gutter,
+            )];
+            spans.extend(line.spans);
+            Line::from(spans)
+        })
+        .collect()
+}
+
 struct CodeBlock {
     language: Option<String>,
     code: String,
@@ -97,3 +260,58 @@ fn language_to_ext(lang: Option<&str>) -> &'static str {
         _ => "txt",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlights_known_languages_and_keeps_prose_order() {
+        let lines = highlight_answer("Intro\n```rust\nfn main() {}\n```\nOutro", Style::default());
+        let text: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        assert_eq!(text, vec!["Intro", "", "fn main() {}", "", "Outro"]);
+        assert!(lines[2].spans.len() > 1, "rust code should be tokenized");
+    }
+
+    #[test]
+    fn highlight_file_numbers_lines_from_the_range_start() {
+        let lines = highlight_file("src/main.rs", "fn main() {\n}\n", 9);
+        let text: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        assert_eq!(text, vec![" 9 │ fn main() {", "10 │ }"]);
+    }
+
+    #[test]
+    fn unknown_language_falls_back_to_plain_text() {
+        let lines = highlight_answer("```nosuchlang\nhello world\n```", Style::default());
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].to_string(), "hello world");
+    }
+
+    #[test]
+    fn renders_basic_markdown_in_prose() {
+        let lines = highlight_answer(
+            "## Summary\n- uses `cfg` for **all** settings\n  - nested\n1. first",
+            Style::default(),
+        );
+        let text: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        assert_eq!(
+            text,
+            vec![
+                "Summary",
+                "  • uses cfg for all settings",
+                "    • nested",
+                "  1. first"
+            ]
+        );
+        assert!(
+            lines[0].spans[0]
+                .style
+                .add_modifier
+                .contains(Modifier::BOLD)
+        );
+        let code = lines[1].spans.iter().find(|s| s.content == "cfg").unwrap();
+        assert_eq!(code.style.fg, Some(Color::Yellow));
+        let bold = lines[1].spans.iter().find(|s| s.content == "all").unwrap();
+        assert!(bold.style.add_modifier.contains(Modifier::BOLD));
+    }
+}
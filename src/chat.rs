@@ -1,12 +1,14 @@
-use crate::{agent, answer, config, diff as diffmod, edits, fsutil};
+use crate::{
+    agent, answer, config, diff as diffmod, edits, fsutil, git, history, llm, project, session,
+    util::{self, truncate_marked},
+};
 use anyhow::{Context, Result};
 use inquire::{Confirm, Password, Select, error::InquireError};
-use regex::Regex;
+use serde::Serialize;
 use std::{
     fs,
-    io::{self, Write},
-    path::PathBuf,
-    time::{SystemTime, UNIX_EPOCH},
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
 };
 use tracing::debug;
 
@@ -49,139 +51,807 @@ const PRESET_MODELS: &[PresetModel] = &[
     },
 ];
 
-pub async fn run(model_override: Option<String>) -> Result<()> {
+/// How the one-shot `--stdin` path reports its turn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Output {
+    /// Progress and diffs as text, asking before each file is written.
+    #[default]
+    Text,
+    /// A single JSON report on stdout; edits are written without asking.
+    Json,
+}
+
+/// A `--stdin` prompt, handled in one turn.
+pub struct OneShot {
+    pub prompt: String,
+    pub output: Output,
+    /// `--yes`: write the edits without asking.
+    pub yes: bool,
+}
+
+/// Fails up front when a `--stdin` turn would have to ask before writing but
+/// there is no terminal to ask on.
+pub fn check_one_shot(interactive: bool, yes: bool, dry_run: bool, output: Output) -> Result<()> {
+    if interactive || yes || dry_run || output == Output::Json {
+        return Ok(());
+    }
+    anyhow::bail!(
+        "standard input is not a terminal, so edits cannot be confirmed; \
+         pass --yes to apply them or --dry-run to only show them"
+    )
+}
+
+pub async fn run(
+    repo_root: PathBuf,
+    model_override: Option<String>,
+    dry_run: bool,
+    quiet: bool,
+    context_globs: Vec<String>,
+    images: Vec<PathBuf>,
+    one_shot: Option<OneShot>,
+) -> Result<()> {
     let mut cfg = config::load()?;
     if let Some(m) = model_override {
-        cfg.provider.model = m;
+        cfg.provider.model = cfg.resolve_model(&m);
     }
+    cfg.runtime.dry_run = dry_run;
+    cfg.runtime.quiet = quiet;
+    cfg.runtime.images = images;
+
+    // JSON output owns stdout, so notices go to stderr instead.
+    let json = matches!(&one_shot, Some(o) if o.output == Output::Json);
+    let notice = |msg: &str| {
+        if json {
+            eprintln!("{msg}");
+        } else {
+            println!("{msg}");
+        }
+    };
 
     // API key check or prompt via /login
-    if cfg.auth.api_key.is_empty() {
-        println!("No API key found. Use /login to set it (or set OPENROUTER_API_KEY).");
+    if cfg.provider.kind.requires_api_key() && cfg.auth.api_key.is_empty() {
+        notice("No API key found. Use /login to set it (or set OPENROUTER_API_KEY).");
     }
 
-    println!("Smol CLI — chat mode. Type /help for commands.");
-    let mut history: Vec<String> = Vec::new();
-    let mut memory: Vec<String> = Vec::new();
-    let mut last_backups: Vec<PathBuf> = Vec::new();
-    let repo_root = std::env::current_dir()?;
+    if !quiet && one_shot.is_none() {
+        println!("Smol CLI — chat mode. Type /help for commands.");
+    }
+    if cfg.runtime.dry_run && !json {
+        println!("Dry run: edits are shown but no files will be written.");
+    }
+    let (_, mut context_warnings) = context_sections(&repo_root, &context_globs, &[]);
+    context_warnings.extend(agent::check_images(&mut cfg));
+    for warning in context_warnings {
+        notice(&format!("Warning: {warning}"));
+    }
+    let mut state = ChatState {
+        repo_root: repo_root.clone(),
+        context_globs,
+        history: Vec::new(),
+        memory: Vec::new(),
+        last_backups: fsutil::BackupStack::default(),
+        session: session::Session::new()?,
+        last_prepared: Vec::new(),
+        spend: Spend::default(),
+        assume_yes: one_shot.as_ref().is_some_and(|o| o.yes),
+    };
 
+    // `--stdin`: one turn, without the REPL or prompt history.
+    if let Some(one_shot) = one_shot {
+        return match one_shot.output {
+            Output::Text => run_turn(&cfg, &repo_root, &one_shot.prompt, &mut state).await,
+            Output::Json => run_json_turn(&cfg, &repo_root, &one_shot.prompt, &mut state).await,
+        };
+    }
+
+    let mut prompt_history = history::PromptHistory::load(!cfg.runtime.dry_run);
     loop {
-        print!("> ");
-        io::stdout().flush().ok();
-        let mut line = String::new();
-        if io::stdin().read_line(&mut line).is_err() {
+        let Some(line) = read_prompt(&prompt_history, quiet)? else {
             break;
-        }
+        };
         let input = line.trim();
         if input.is_empty() {
             continue;
         }
+        prompt_history.push(input);
 
         if input.starts_with('/') {
-            match handle_slash(input, &mut cfg, &mut history, &mut last_backups).await? {
+            match handle_slash(input, &mut cfg, &mut state).await? {
                 Flow::Continue => continue,
                 Flow::Exit => break,
+                Flow::Retry => {
+                    let Some(prompt) = state.history.last().cloned() else {
+                        println!("Nothing to retry.");
+                        continue;
+                    };
+                    let mut retry_cfg = cfg.clone();
+                    retry_cfg.runtime.temperature =
+                        agent::retry_temperature(cfg.runtime.temperature);
+                    println!(
+                        "Retrying: {prompt} (temperature {:.1})",
+                        retry_cfg.runtime.temperature
+                    );
+                    run_turn(&retry_cfg, &repo_root, &prompt, &mut state).await?;
+                }
+                Flow::Prompt(prompt) => {
+                    run_turn(&cfg, &repo_root, &prompt, &mut state).await?;
+                    state.history.push(prompt);
+                }
             }
         } else {
-            let ctx = build_context(&memory)?;
-            let agent_outcome = agent::run(&cfg, &repo_root, input, ctx).await?;
-
-            if !agent_outcome.plan.is_empty() {
-                println!("Plan:");
-                for (idx, step) in agent_outcome.plan.iter().enumerate() {
-                    let mut annotations = Vec::new();
-                    if let Some(path) = &step.read {
-                        annotations.push(format!("read {}", path));
-                    }
-                    if let Some(path) = &step.create {
-                        annotations.push(format!("create {}", path));
-                    }
-                    if annotations.is_empty() {
-                        println!("  {}. {}", idx + 1, step.description);
-                    } else {
-                        println!(
-                            "  {}. {} [{}]",
-                            idx + 1,
-                            step.description,
-                            annotations.join(", ")
-                        );
-                    }
+            run_turn(&cfg, &repo_root, input, &mut state).await?;
+            state.history.push(input.to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads one line after a "> " prompt, with Up/Down recalling earlier inputs
+/// when stdin is a terminal. Piped input is read plainly, without the prompt
+/// when `quiet`. `None` on end of input or Ctrl+C/Ctrl+D.
+fn read_prompt(prompt_history: &history::PromptHistory, quiet: bool) -> Result<Option<String>> {
+    let interactive = io::stdin().is_terminal();
+    if interactive || !quiet {
+        print!("> ");
+        io::stdout().flush().ok();
+    }
+    if !interactive {
+        let mut line = String::new();
+        return match io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => Ok(None),
+            Ok(_) => Ok(Some(line)),
+        };
+    }
+    crossterm::terminal::enable_raw_mode().context("enable raw mode")?;
+    let result = edit_line(prompt_history.entries());
+    crossterm::terminal::disable_raw_mode().ok();
+    println!();
+    result
+}
+
+fn edit_line(entries: &[String]) -> Result<Option<String>> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+
+    let mut buffer: Vec<char> = Vec::new();
+    let mut cursor = 0;
+    let mut recall = history::Recall::default();
+    loop {
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        match key.code {
+            KeyCode::Char('c') | KeyCode::Char('d') if ctrl => return Ok(None),
+            KeyCode::Enter => return Ok(Some(buffer.into_iter().collect())),
+            // Other Ctrl/Alt chords are not bound here; AltGr (Ctrl+Alt on
+            // Windows) still types its character.
+            KeyCode::Char(c) if ctrl == alt => {
+                buffer.insert(cursor, c);
+                cursor += 1;
+            }
+            KeyCode::Backspace if cursor > 0 => {
+                cursor -= 1;
+                buffer.remove(cursor);
+            }
+            KeyCode::Delete if cursor < buffer.len() => {
+                buffer.remove(cursor);
+            }
+            KeyCode::Left => cursor = cursor.saturating_sub(1),
+            KeyCode::Right => cursor = (cursor + 1).min(buffer.len()),
+            KeyCode::Home => cursor = 0,
+            KeyCode::End => cursor = buffer.len(),
+            KeyCode::Up | KeyCode::Down => {
+                let recalled = if key.code == KeyCode::Up {
+                    let current: String = buffer.iter().collect();
+                    recall.older(entries, &current)
+                } else {
+                    recall.newer(entries)
+                };
+                if let Some(text) = recalled {
+                    buffer = text
+                        .chars()
+                        .map(|c| if c == '\n' { ' ' } else { c })
+                        .collect();
+                    cursor = buffer.len();
                 }
             }
+            _ => continue,
+        }
+        let line: String = buffer.iter().collect();
+        let mut stdout = io::stdout();
+        crossterm::execute!(
+            stdout,
+            crossterm::cursor::MoveToColumn(0),
+            crossterm::terminal::Clear(crossterm::terminal::ClearType::CurrentLine),
+            crossterm::style::Print(format!("> {line}")),
+            crossterm::cursor::MoveToColumn((2 + cursor) as u16),
+        )?;
+    }
+}
 
-            for log in &agent_outcome.reads {
-                println!("{}", agent::format_read_log(log));
-            }
+/// Runs one prompt through the agent, printing the plan and answer or
+/// applying the proposed edits, and records the turn in memory and the session.
+async fn run_turn(
+    cfg: &config::AppConfig,
+    repo_root: &Path,
+    input: &str,
+    state: &mut ChatState,
+) -> Result<()> {
+    let ctx = build_context(repo_root, &state.context_globs, &state.memory)?;
+    if agent::repo_looks_empty(repo_root) {
+        println!("{}", agent::EMPTY_REPO_HINT);
+    }
+    let agent_outcome = agent::run(cfg, repo_root, input, ctx, None).await?;
 
-            for log in &agent_outcome.creates {
-                println!("{}", agent::format_create_log(log));
+    if !agent_outcome.plan.is_empty() && !cfg.runtime.quiet {
+        println!("Plan:");
+        for (idx, step) in agent_outcome.plan.iter().enumerate() {
+            let mut annotations = Vec::new();
+            if let Some(path) = &step.read {
+                annotations.push(format!("read {}", path));
+            }
+            if let Some(path) = &step.create {
+                annotations.push(format!("create {}", path));
             }
+            if annotations.is_empty() {
+                println!("  {}. {}", idx + 1, step.description);
+            } else {
+                println!(
+                    "  {}. {} [{}]",
+                    idx + 1,
+                    step.description,
+                    annotations.join(", ")
+                );
+            }
+        }
+    }
+
+    if !cfg.runtime.quiet {
+        for log in &agent_outcome.reads {
+            println!("{}", agent::format_read_log(log));
+        }
+        for log in &agent_outcome.creates {
+            println!("{}", agent::format_create_log(log));
+        }
+    }
+    for warning in agent_outcome
+        .reads
+        .iter()
+        .filter_map(agent::injection_warning)
+    {
+        println!("Warning: {warning}");
+    }
 
-            debug!("LLM raw: {}", agent_outcome.response.content);
+    debug!("LLM raw: {}", agent_outcome.response.content);
+    state.spend.record(
+        cfg,
+        agent_outcome.response.usage.as_ref(),
+        agent_outcome.response.served_by.as_deref(),
+    );
 
-            let mut summary = agent::summarize_turn(input, &agent_outcome);
+    let mut summary = agent::summarize_turn(input, &agent_outcome);
 
-            if agent_outcome.is_treated_as_info {
-                let formatted = answer::format_answer(&agent_outcome.response.content);
-                if formatted.trim().is_empty() {
-                    println!("No response from model.");
-                } else {
-                    println!("{}", formatted);
+    if let Some(reasoning) = &agent_outcome.response.reasoning {
+        println!("Reasoning:\n{reasoning}");
+    }
+
+    if let Some(fallback) = &agent_outcome.response.served_by {
+        println!("Warning: the primary model failed; fallback {fallback} answered.");
+    }
+
+    if agent_outcome.nudged && !cfg.runtime.quiet {
+        println!("No edits proposed; asked the model once more.");
+    }
+
+    if agent_outcome.is_treated_as_info {
+        let formatted = if cfg.runtime.answer_as_diff {
+            answer::format_answer(&agent_outcome.response.content)
+        } else {
+            agent_outcome.response.content.trim().to_string()
+        };
+        if formatted.trim().is_empty() {
+            println!("No response from model.");
+        } else {
+            print_paged(&formatted);
+        }
+    } else {
+        let mut parse_failed = false;
+        match agent_outcome.response.edit_batch() {
+            Ok(mut batch) => {
+                for warning in edits::dedupe_batch(&mut batch, repo_root) {
+                    println!("Warning: {warning}");
                 }
-            } else {
-                let mut parse_failed = false;
-                match edits::parse_edits(&agent_outcome.response.content) {
-                    Ok(batch) => {
-                        apply_with_review(batch, &mut last_backups)?;
-                    }
-                    Err(e) => {
-                        parse_failed = true;
-                        println!("Model did not return valid edits JSON: {e}");
-                        println!("Raw response:\n{}", agent_outcome.response.content);
-                    }
+                state.last_prepared =
+                    edits::prepare_batch(&batch, repo_root, cfg.runtime.normalize_newlines);
+                if cfg.runtime.dry_run {
+                    preview_batch(&state.last_prepared);
+                } else if state.assume_yes {
+                    apply_without_review(
+                        cfg,
+                        repo_root,
+                        input,
+                        &state.last_prepared,
+                        &mut state.last_backups,
+                    )?;
+                } else {
+                    apply_with_review(cfg, repo_root, input, batch, &mut state.last_backups)?;
                 }
+            }
+            Err(e) => {
+                parse_failed = true;
+                println!("Model did not return valid edits JSON: {e}");
+                println!("Raw response:\n{}", agent_outcome.response.content);
+            }
+        }
+
+        if parse_failed {
+            summary.push_str("\nParse error when applying edits.");
+        }
+    }
 
-                if parse_failed {
-                    summary.push_str("\nParse error when applying edits.");
+    if !cfg.runtime.dry_run
+        && let Err(err) =
+            state
+                .session
+                .record(session::SessionTurn::new(input, &agent_outcome, &summary))
+    {
+        println!("Warning: failed to save session: {err}");
+    }
+
+    state.memory.push(summary);
+    if state.memory.len() > agent::MEMORY_LIMIT {
+        state.memory.remove(0);
+    }
+    if agent::needs_compaction(&state.memory, cfg.runtime.compact_memory_bytes) {
+        compact(cfg, &mut state.memory, &mut state.spend).await;
+    }
+
+    Ok(())
+}
+
+/// `--output json`: what a one-shot turn planned, read, proposed and wrote.
+#[derive(Serialize)]
+struct TurnReport<'a> {
+    prompt: &'a str,
+    plan: &'a [agent::PlanStep],
+    reads: &'a [agent::ReadLog],
+    creates: &'a [agent::CreateLog],
+    /// The model's reply when the turn was treated as a question.
+    answer: Option<String>,
+    edits: Vec<ReportedEdit>,
+    usage: Option<&'a llm::Usage>,
+    dry_run: bool,
+    /// Whether `edits` were written to disk.
+    applied: bool,
+    commit: Option<String>,
+    warnings: Vec<String>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ReportedEdit {
+    path: String,
+    created: bool,
+    rationale: Option<String>,
+    stats: String,
+    diff: String,
+}
+
+impl From<&edits::PreparedEdit> for ReportedEdit {
+    fn from(p: &edits::PreparedEdit) -> Self {
+        Self {
+            path: p.path.clone(),
+            created: !p.existed,
+            rationale: p.rationale.clone(),
+            stats: p.stats.to_string(),
+            diff: p.unified_diff(),
+        }
+    }
+}
+
+/// `--output json` counterpart of [`run_turn`]: writes the edits without
+/// asking, unless this is a dry run, and prints one [`TurnReport`].
+async fn run_json_turn(
+    cfg: &config::AppConfig,
+    repo_root: &Path,
+    input: &str,
+    state: &mut ChatState,
+) -> Result<()> {
+    let ctx = build_context(repo_root, &state.context_globs, &state.memory)?;
+    let outcome = agent::run(cfg, repo_root, input, ctx, None).await?;
+    state.spend.record(
+        cfg,
+        outcome.response.usage.as_ref(),
+        outcome.response.served_by.as_deref(),
+    );
+
+    let mut warnings: Vec<String> = outcome
+        .reads
+        .iter()
+        .filter_map(agent::injection_warning)
+        .collect();
+    if let Some(fallback) = &outcome.response.served_by {
+        warnings.push(format!(
+            "the primary model failed; fallback {fallback} answered."
+        ));
+    }
+
+    let mut report = TurnReport {
+        prompt: input,
+        plan: &outcome.plan,
+        reads: &outcome.reads,
+        creates: &outcome.creates,
+        answer: None,
+        edits: Vec::new(),
+        usage: outcome.response.usage.as_ref(),
+        dry_run: cfg.runtime.dry_run,
+        applied: false,
+        commit: None,
+        warnings: Vec::new(),
+        error: None,
+    };
+    let mut summary = agent::summarize_turn(input, &outcome);
+
+    if outcome.is_treated_as_info {
+        report.answer = Some(outcome.response.content.trim().to_string());
+    } else {
+        match outcome.response.edit_batch() {
+            Ok(mut batch) => {
+                warnings.extend(edits::dedupe_batch(&mut batch, repo_root));
+                let prepared =
+                    edits::prepare_batch(&batch, repo_root, cfg.runtime.normalize_newlines);
+                report.edits = prepared.iter().map(ReportedEdit::from).collect();
+                if !cfg.runtime.dry_run && !prepared.is_empty() {
+                    report.commit = apply_prepared(
+                        cfg,
+                        repo_root,
+                        input,
+                        &prepared,
+                        &mut state.last_backups,
+                        &mut warnings,
+                    )?;
+                    report.applied = true;
                 }
+                state.last_prepared = prepared;
             }
-
-            memory.push(summary);
-            if memory.len() > 6 {
-                memory.remove(0);
+            Err(e) => {
+                report.error = Some(format!("model did not return valid edits JSON: {e}"));
+                summary.push_str("\nParse error when applying edits.");
             }
+        }
+    }
+
+    if !cfg.runtime.dry_run
+        && let Err(err) = state
+            .session
+            .record(session::SessionTurn::new(input, &outcome, &summary))
+    {
+        warnings.push(format!("failed to save session: {err}"));
+    }
+    report.warnings = warnings;
+
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
 
-            history.push(input.to_string());
+/// The lines of `text` that `range` selects, each prefixed with its number.
+fn numbered(text: &str, range: agent::LineRange) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.is_empty() {
+        return "(empty file)".to_string();
+    }
+    let (start, end) = range.bounds(lines.len());
+    let width = end.to_string().len();
+    (start..=end)
+        .map(|n| format!("{n:>width$} │ {}", lines[n - 1]))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Prints an answer, paging it when it is taller than the terminal: through
+/// `$PAGER` if set, otherwise a screenful at a time. Output that is not a
+/// terminal is printed whole.
+fn print_paged(text: &str) {
+    let height = crossterm::terminal::size().map_or(24, |(_, rows)| rows as usize);
+    let page = height.saturating_sub(1).max(1);
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.len() <= page || !io::stdout().is_terminal() || !io::stdin().is_terminal() {
+        println!("{text}");
+        return;
+    }
+    if let Ok(pager) = std::env::var("PAGER")
+        && !pager.trim().is_empty()
+    {
+        match run_pager(&pager, text) {
+            Ok(()) => return,
+            Err(err) => debug!("pager {pager:?} failed: {err:#}"),
         }
     }
+    for (idx, chunk) in lines.chunks(page).enumerate() {
+        if idx > 0 {
+            print!("— press Enter for more, q to stop —");
+            io::stdout().flush().ok();
+            let mut reply = String::new();
+            if io::stdin().read_line(&mut reply).is_err() || reply.trim() == "q" {
+                let shown = idx * page;
+                println!("({} more lines not shown)", lines.len() - shown);
+                return;
+            }
+        }
+        println!("{}", chunk.join("\n"));
+    }
+}
 
+/// Pipes `text` into `$PAGER`, run through the shell so it may carry flags
+/// such as `less -R`.
+fn run_pager(pager: &str, text: &str) -> Result<()> {
+    let mut child = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(pager)
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .context("start pager")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager may quit before reading everything; that is fine.
+        let _ = writeln!(stdin, "{text}");
+    }
+    let status = child.wait().context("wait for pager")?;
+    if !status.success() {
+        anyhow::bail!("pager exited with {status}");
+    }
     Ok(())
 }
 
+/// Replaces memory with a model-written summary and reports the savings.
+async fn compact(cfg: &config::AppConfig, memory: &mut Vec<String>, spend: &mut Spend) {
+    match agent::compact_memory(cfg, memory).await {
+        Ok(compaction) => {
+            spend.record(cfg, compaction.usage.as_ref(), None);
+            println!("{}", compaction.report());
+            *memory = vec![compaction.summary];
+        }
+        Err(err) => println!("Compaction failed: {err:#}"),
+    }
+}
+
 enum Flow {
     Continue,
     Exit,
+    /// Re-run the last prompt (`/retry`).
+    Retry,
+    /// Run this prompt as if it had been typed (`/use`).
+    Prompt(String),
+}
+
+/// Per-session REPL state shared between turns and slash commands.
+struct ChatState {
+    repo_root: PathBuf,
+    /// `--context` globs, re-read into every prompt's context.
+    context_globs: Vec<String>,
+    history: Vec<String>,
+    memory: Vec<String>,
+    last_backups: fsutil::BackupStack,
+    session: session::Session,
+    /// Edits resolved for the last batch, for `/diff` and `/export`.
+    last_prepared: Vec<edits::PreparedEdit>,
+    spend: Spend,
+    /// `--yes`: write edits without asking.
+    assume_yes: bool,
+}
+
+/// Running usage totals for `/cost`.
+#[derive(Default)]
+struct Spend {
+    dollars: f64,
+    tokens: u64,
+}
+
+impl Spend {
+    /// Adds a response's usage. When the provider reports no cost, it is
+    /// estimated from the cached catalog prices of `served_by` (the fallback
+    /// that answered) or else the configured model.
+    fn record(
+        &mut self,
+        cfg: &config::AppConfig,
+        usage: Option<&llm::Usage>,
+        served_by: Option<&str>,
+    ) {
+        let Some(usage) = usage else {
+            return;
+        };
+        let id = served_by.unwrap_or(&cfg.provider.model);
+        let model = usage
+            .total_cost
+            .is_none()
+            .then(|| llm::cached_models(cfg))
+            .flatten()
+            .and_then(|models| models.into_iter().find(|m| m.id == id));
+        self.dollars += usage.cost(model.as_ref()).unwrap_or(0.0);
+        self.tokens += usage.total_tokens.unwrap_or(0) as u64;
+    }
 }
 
 async fn handle_slash(
     input: &str,
     cfg: &mut config::AppConfig,
-    history: &mut Vec<String>,
-    last_backups: &mut Vec<PathBuf>,
+    state: &mut ChatState,
 ) -> Result<Flow> {
+    let ChatState {
+        repo_root,
+        context_globs,
+        history,
+        memory,
+        last_backups,
+        session,
+        last_prepared,
+        spend,
+        ..
+    } = state;
     match input {
         "/help" => {
-            println!("/login  /model  /clear  /undo  /stats  /quit");
+            println!(
+                "/login  /model  /clear  /new  /retry  /explain  /open  /use  /undo  /revert  /backups  /rollback  /diff  /export  /info  /stats  /cost  /tokens  /compact  /save  /sessions  /load  /quit"
+            );
+        }
+        cmd if cmd.split_whitespace().next() == Some("/save") => {
+            match cmd.split_whitespace().nth(1) {
+                Some(name) => match session.save_as(name) {
+                    Ok(path) => println!("Saved session as {}", path.display()),
+                    Err(e) => println!("Save failed: {e:#}"),
+                },
+                None => match session.flush() {
+                    Ok(()) if session.saved_turns() == 0 => println!("Nothing to save yet."),
+                    Ok(()) => println!(
+                        "Saved {} turns to {}",
+                        session.saved_turns(),
+                        session.path().display()
+                    ),
+                    Err(e) => println!("Save failed: {e}"),
+                },
+            }
+        }
+        "/sessions" => match session::list_sessions() {
+            Ok(sessions) if sessions.is_empty() => println!("No saved sessions."),
+            Ok(sessions) => {
+                for path in sessions {
+                    let turns = fs::read_to_string(&path)
+                        .map(|s| s.lines().filter(|l| !l.trim().is_empty()).count())
+                        .unwrap_or(0);
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    println!("{name} ({turns} turns)");
+                }
+            }
+            Err(e) => println!("Failed to list sessions: {e}"),
+        },
+        cmd if cmd.split_whitespace().next() == Some("/load") => {
+            match cmd.split_whitespace().nth(1) {
+                Some(name) => match session::load(name) {
+                    Ok(loaded) => {
+                        *memory = loaded.memory(agent::MEMORY_LIMIT);
+                        println!("{}", loaded.describe(agent::MEMORY_LIMIT));
+                        if loaded.skipped > 0 {
+                            println!("Warning: skipped {} malformed lines.", loaded.skipped);
+                        }
+                    }
+                    Err(e) => println!("Warning: could not load session: {e}"),
+                },
+                None => println!("Usage: /load <session-file> (see /sessions)"),
+            }
+        }
+        "/diff" => {
+            if last_prepared.is_empty() {
+                println!("No edits from the last turn.");
+            } else {
+                print!("{}", edits::combined_diff(last_prepared));
+            }
+        }
+        cmd if cmd.starts_with("/export") => match cmd.split_whitespace().nth(1) {
+            Some(_) if last_prepared.is_empty() => println!("No edits from the last turn."),
+            Some(file) => match edits::export_patch(repo_root, file, last_prepared) {
+                Ok(path) => println!("Wrote patch to {}", path.display()),
+                Err(e) => println!("Export failed: {e:#}"),
+            },
+            None => println!("Usage: /export <file.patch>"),
+        },
+        cmd if cmd.starts_with("/explain") => match cmd.split_whitespace().nth(1) {
+            Some(rel) => match agent::explain_context(repo_root, rel) {
+                Ok((context, read)) => {
+                    if let Some(warning) = agent::injection_warning(&read) {
+                        println!("Warning: {warning}");
+                    }
+                    let outcome = agent::explain(cfg, repo_root, rel, context, read).await?;
+                    spend.record(
+                        cfg,
+                        outcome.response.usage.as_ref(),
+                        outcome.response.served_by.as_deref(),
+                    );
+                    print_paged(outcome.response.content.trim());
+                    memory.push(agent::summarize_turn(&agent::explain_prompt(rel), &outcome));
+                    if memory.len() > agent::MEMORY_LIMIT {
+                        memory.remove(0);
+                    }
+                }
+                Err(e) => println!("Cannot explain: {e:#}"),
+            },
+            None => println!("Usage: /explain <path>"),
+        },
+        cmd if cmd.split_whitespace().next() == Some("/open") => {
+            let mut args = cmd.split_whitespace().skip(1);
+            let path = args.next();
+            let range = args.next().map(agent::LineRange::parse);
+            let (Some(path), None | Some(Some(_))) = (path, range) else {
+                println!("Usage: /open <path> [start-end], e.g. /open src/llm.rs 40-80");
+                return Ok(Flow::Continue);
+            };
+            match agent::open_file(repo_root, path) {
+                Ok(text) => print_paged(&numbered(&text, range.flatten().unwrap_or_default())),
+                Err(e) => println!("Cannot open: {e:#}"),
+            }
+        }
+        "/retry" => return Ok(Flow::Retry),
+        cmd if cmd.split_whitespace().next() == Some("/use") => {
+            let mut args = cmd.split_whitespace().skip(1);
+            let names = cfg.templates.keys().cloned().collect::<Vec<_>>().join(", ");
+            let Some(name) = args.next() else {
+                println!("Usage: /use <name> [file]. Templates: {names}");
+                return Ok(Flow::Continue);
+            };
+            let selection = cfg
+                .templates
+                .get(name)
+                .filter(|text| text.contains("{selection}"))
+                .and_then(|_| arboard::Clipboard::new().ok()?.get_text().ok());
+            match cfg.expand_template(name, args.next(), selection.as_deref()) {
+                Some(prompt) => {
+                    println!("> {prompt}");
+                    return Ok(Flow::Prompt(prompt));
+                }
+                None => println!("Unknown template '{name}'. Templates: {names}"),
+            }
         }
         "/quit" | "/exit" => return Ok(Flow::Exit),
         "/clear" => {
+            crossterm::execute!(
+                io::stdout(),
+                crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
+                crossterm::cursor::MoveTo(0, 0)
+            )?;
+        }
+        "/new" => {
             history.clear();
-            println!("History cleared.");
+            memory.clear();
+            last_prepared.clear();
+            *spend = Spend::default();
+            println!("Started new conversation.");
+        }
+        "/compact" => {
+            if memory.is_empty() {
+                println!("Nothing to compact.");
+            } else {
+                compact(cfg, memory, spend).await;
+            }
         }
         "/stats" => {
             println!("Messages: {}", history.len());
         }
+        "/info" => println!("{}", agent::info(cfg, repo_root)),
+        "/tokens" => {
+            let (mut sections, _) = context_sections(repo_root, context_globs, memory);
+            if let Some(prompt) = history.last() {
+                sections.push(("prompt (last)".to_string(), prompt.clone()));
+            }
+            println!("{}", agent::token_table(&sections));
+            println!("Estimated at ~4 characters per token; files read during planning are extra.");
+        }
+        "/cost" => println!(
+            "Session cost: ${:.4} over {} tokens",
+            spend.dollars, spend.tokens
+        ),
         "/login" => {
             let key = Password::new("OpenRouter API key (sk-...):")
                 .without_confirmation()
@@ -201,37 +871,94 @@ async fn handle_slash(
                     }
                     None => println!("Model selection cancelled."),
                 }
+            } else if parts[1..] == ["current"] {
+                match llm::find_model(cfg, &cfg.provider.model).await {
+                    Ok(Some(model)) => println!("{}", model.details()),
+                    Ok(None) => println!(
+                        "Model {} is not in the provider's catalog; no details known.",
+                        cfg.provider.model
+                    ),
+                    Err(e) => println!("Failed to fetch models: {e:#}"),
+                }
             } else if parts.len() == 2 {
-                cfg.provider.model = parts[1].to_string();
+                cfg.provider.model = cfg.resolve_model(parts[1]);
                 config::save(cfg)?;
-                println!("Model set to {}", cfg.provider.model);
+                if cfg.provider.model == parts[1] {
+                    println!("Model set to {}", cfg.provider.model);
+                } else {
+                    println!("Model set to {} (alias {})", cfg.provider.model, parts[1]);
+                }
             } else {
-                println!("Usage: /model [<provider/model>], e.g., grok-4-fast:free");
+                println!(
+                    "Usage: /model [<provider/model> | <alias> | current], e.g., grok-4-fast:free or sonnet"
+                );
             }
         }
-        "/undo" => {
-            if let Some(b) = last_backups.pop() {
-                if let Some(target) = target_from_backup(&b) {
-                    if !b.exists() {
-                        match fs::remove_file(&target) {
-                            Ok(_) => println!("Removed {}", target.display()),
-                            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                                println!("Nothing to undo for {}", target.display())
-                            }
-                            Err(e) => println!("Undo failed: {e}"),
-                        }
-                    } else {
-                        if let Err(e) = fs::copy(&b, &target) {
-                            println!("Undo failed: {e}");
-                        } else {
-                            println!("Reverted {}", target.display());
-                        }
+        cmd if cmd.split_whitespace().next() == Some("/undo") => {
+            let Some(count) = util::parse_undo_count(cmd) else {
+                println!("Usage: /undo [<count>]");
+                return Ok(Flow::Continue);
+            };
+            let mut undone = 0;
+            while undone < count {
+                let Some(backup) = last_backups.pop_file() else {
+                    break;
+                };
+                restore(repo_root, &backup);
+                undone += 1;
+            }
+            if undone == 0 {
+                println!("Nothing to undo.");
+            } else if undone < count {
+                println!("Undid {undone} of {count} requested edits; nothing left to undo.");
+            } else if count > 1 {
+                println!("Undid {undone} edits.");
+            }
+        }
+        "/revert" => match last_backups.pop_batch() {
+            Some(batch) => {
+                if let Some(bad) = batch
+                    .iter()
+                    .find(|b| fsutil::target_from_backup(repo_root, b).is_none())
+                {
+                    println!(
+                        "No target path found for backup {}; nothing was reverted.",
+                        bad.display()
+                    );
+                    last_backups.begin_batch();
+                    for backup in batch.into_iter().rev() {
+                        last_backups.push(backup);
                     }
                 } else {
-                    println!("No target path found for backup {}", b.display());
+                    for backup in &batch {
+                        restore(repo_root, backup);
+                    }
+                }
+            }
+            None => println!("Nothing to revert."),
+        },
+        "/backups" => match fsutil::list_backup_sets() {
+            Ok(sets) if sets.is_empty() => println!("No backups yet."),
+            Ok(sets) => {
+                for (timestamp, files) in sets {
+                    println!("{timestamp} ({files} files)");
                 }
-            } else {
-                println!("Nothing to undo.");
+            }
+            Err(e) => println!("Failed to list backups: {e:#}"),
+        },
+        cmd if cmd.split_whitespace().next() == Some("/rollback") => {
+            match cmd.split_whitespace().nth(1) {
+                Some(timestamp) => match fsutil::backup_set(timestamp) {
+                    Ok(set) => {
+                        let restored = set.iter().filter(|b| restore(repo_root, b)).count();
+                        println!(
+                            "Rolled back {timestamp}: restored {restored} of {} files.",
+                            set.len()
+                        );
+                    }
+                    Err(e) => println!("Rollback failed: {e:#}"),
+                },
+                None => println!("Usage: /rollback <timestamp> (see /backups)"),
             }
         }
         _ => println!("Unknown command. /help"),
@@ -239,19 +966,25 @@ async fn handle_slash(
     Ok(Flow::Continue)
 }
 
-fn target_from_backup(backup_path: &PathBuf) -> Option<PathBuf> {
-    // backup structure: .smol/backups/<ts>/RELATIVE/PATH
-    // reconstruct target by removing ".smol/backups/<ts>/" prefix
-    let smol = fsutil::smol_dir().ok()?;
-    let backups = smol.join("backups");
-    let rel = backup_path.strip_prefix(backups).ok()?;
-    let comps: Vec<_> = rel.components().collect();
-    if comps.len() >= 2 {
-        // skip timestamp component
-        let stripped: PathBuf = comps.iter().skip(1).collect();
-        Some(std::env::current_dir().ok()?.join(stripped))
-    } else {
-        None
+/// Restores one backup and reports it. Returns whether the file changed.
+fn restore(repo_root: &Path, backup: &Path) -> bool {
+    match fsutil::restore_backup(repo_root, backup) {
+        Ok((target, fsutil::Restored::Reverted)) => {
+            println!("Reverted {}", target.display());
+            true
+        }
+        Ok((target, fsutil::Restored::Removed)) => {
+            println!("Removed {}", target.display());
+            true
+        }
+        Ok((target, fsutil::Restored::Missing)) => {
+            println!("Nothing to undo for {}", target.display());
+            false
+        }
+        Err(e) => {
+            println!("Undo failed: {e:#}");
+            false
+        }
     }
 }
 
@@ -264,63 +997,128 @@ fn prompt_for_model() -> Result<Option<PresetModel>> {
     }
 }
 
-fn build_context(memory: &[String]) -> Result<String> {
-    let mut ctx = String::new();
-    if let Ok(readme) = fs::read_to_string("README.md") {
-        ctx.push_str("README.md:\n");
-        ctx.push_str(&truncate(&readme, 10_000));
+fn build_context(repo_root: &Path, context_globs: &[String], memory: &[String]) -> Result<String> {
+    Ok(context_sections(repo_root, context_globs, memory)
+        .0
+        .into_iter()
+        .map(|(_, text)| text)
+        .collect())
+}
+
+/// The pieces [`build_context`] concatenates, labelled for `/tokens`. Files
+/// from `--context` come first, after the detected project type; a
+/// `.smolcontext` manifest replaces the README.
+fn context_sections(
+    repo_root: &Path,
+    context_globs: &[String],
+    memory: &[String],
+) -> (Vec<(String, String)>, Vec<String>) {
+    let (mut sections, mut warnings) = agent::seeded_context(repo_root, context_globs);
+    if let Some(summary) = project::summary(repo_root) {
+        sections.insert(0, ("project type".to_string(), format!("{summary}\n")));
+    }
+    if let Some((manifest, manifest_warnings)) = agent::manifest_context(repo_root) {
+        sections.extend(manifest);
+        warnings.extend(manifest_warnings);
+    } else if let Ok(fsutil::FileText::Text(readme)) =
+        fsutil::read_text(&repo_root.join("README.md"))
+    {
+        sections.push((
+            if readme.len() > 10_000 {
+                "README.md (truncated)".to_string()
+            } else {
+                "README.md".to_string()
+            },
+            format!(
+                "README.md:\n{}",
+                agent::context_file("README.md", truncate_marked(&readme, 10_000), &mut warnings)
+            ),
+        ));
     }
     if !memory.is_empty() {
-        ctx.push_str("\n\n# Conversation\n");
+        let mut text = "\n\n# Conversation\n".to_string();
         for entry in memory {
-            ctx.push_str(entry);
-            ctx.push_str("\n---\n");
+            text.push_str(entry);
+            text.push_str("\n---\n");
         }
+        sections.push((format!("memory ({} turns)", memory.len()), text));
     }
-    Ok(ctx)
+    (sections, warnings)
 }
 
-fn truncate(s: &str, max: usize) -> String {
-    if s.len() <= max {
-        s.to_string()
-    } else {
-        s[..max].to_string()
+/// `--dry-run` counterpart of [`apply_with_review`]: prints each resolved diff
+/// without prompting or writing.
+fn preview_batch(prepared: &[edits::PreparedEdit]) {
+    if prepared.is_empty() {
+        println!("No applicable edits.");
+        return;
     }
+    for p in prepared {
+        println!("\n— Proposed edit (dry run) —");
+        println!("{}", p.path);
+        println!("────────────────────────────────────────────────");
+        println!("{}", p.unified_diff());
+        if let Some(r) = &p.rationale {
+            println!("Reason: {}", r);
+        }
+    }
+    println!(
+        "\nDry run: {} file(s) would change; nothing was written.",
+        prepared.len()
+    );
 }
 
-fn timestamp_dir() -> Result<PathBuf> {
-    let smol = fsutil::smol_dir()?;
-    let backups = smol.join("backups");
-    std::fs::create_dir_all(&backups).ok();
-    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
-    let p = backups.join(format!("{}", now));
-    std::fs::create_dir_all(&p).ok();
-    Ok(p)
-}
-
-fn is_write_blocked(path: &str) -> bool {
-    // Cheap guard: block edits outside project or hidden/system paths
-    let re = Regex::new(r#"(^/|^\.)"#).unwrap();
-    re.is_match(path)
-}
-
-fn apply_with_review(batch: edits::EditBatch, last_backups: &mut Vec<PathBuf>) -> Result<()> {
-    use std::{io::ErrorKind, path::Path};
+fn apply_with_review(
+    cfg: &config::AppConfig,
+    root: &Path,
+    prompt: &str,
+    batch: edits::EditBatch,
+    last_backups: &mut fsutil::BackupStack,
+) -> Result<()> {
+    use std::io::ErrorKind;
     if batch.edits.is_empty() {
         println!("No edits proposed.");
         return Ok(());
     }
 
-    let root = std::env::current_dir()?;
-    let backup_root = timestamp_dir()?;
+    // One gate up front, so an obviously wrong batch can be dropped without
+    // paging through every diff. A lone edit gets its own prompt below.
+    let cap = cfg.runtime.max_edits_per_batch;
+    let count = edits::effective_edit_count(&batch, root);
+    let over_cap = cap > 0 && count > cap;
+    if batch.edits.len() > 1 || over_cap {
+        let summary = edits::batch_summary(&batch);
+        println!("Proposed edits:");
+        for line in &summary {
+            println!("  {line}");
+        }
+        if over_cap {
+            println!("The model proposed {count} edits, more than max_edits_per_batch ({cap}).");
+        }
+        let review = Confirm::new(&format!(
+            "Review {} edits across {} files?",
+            batch.edits.len(),
+            summary.len()
+        ))
+        .with_default(false)
+        .prompt()?;
+        if !review {
+            println!("Discarded the proposed edits.");
+            return Ok(());
+        }
+    }
+
+    let backup_root = fsutil::timestamp_dir()?;
+    last_backups.begin_batch();
+    let mut applied = Vec::new();
 
     for e in &batch.edits {
-        if is_write_blocked(&e.path) {
+        if fsutil::is_write_blocked(&e.path) {
             println!("Skipping suspicious path: {}", e.path);
             continue;
         }
 
-        let abs = fsutil::ensure_inside_repo(&root, Path::new(&e.path))
+        let abs = fsutil::ensure_inside_repo(root, Path::new(&e.path))
             .with_context(|| format!("invalid path {}", e.path))?;
 
         let (old, existed) = match std::fs::read_to_string(&abs) {
@@ -333,7 +1131,7 @@ fn apply_with_review(batch: edits::EditBatch, last_backups: &mut Vec<PathBuf>) -
         };
 
         let new = match edits::apply_edit(&old, e) {
-            Ok(n) => n,
+            Ok(n) => edits::normalize_new_file(&old, n, cfg.runtime.normalize_newlines),
             Err(err) => {
                 println!("Skipping {}: {}", e.path, err);
                 continue;
@@ -347,20 +1145,30 @@ fn apply_with_review(batch: edits::EditBatch, last_backups: &mut Vec<PathBuf>) -
 
         // Show diff
         let udiff = diffmod::unified_diff(&old, &new, &e.path);
-        println!("\n{}", "— Proposed edit —");
-        println!("{}", e.path);
-        println!("{}", "────────────────────────────────────────────────");
+        println!("\n— Proposed edit —");
+        println!("{}  {}", e.path, diffmod::LineStats::between(&old, &new));
+        println!("────────────────────────────────────────────────");
         println!("{}", udiff);
         if let Some(r) = &e.rationale {
             println!("Reason: {}", r);
         }
+        if let Some(warning) = edits::shrink_warning(e, &old, &new) {
+            println!("Warning: {warning}");
+        }
+
+        if cfg.runtime.warn_dirty_files && git::is_dirty(root, Path::new(&e.path)) {
+            println!(
+                "Warning: {} has uncommitted changes that this edit will overwrite.",
+                e.path
+            );
+        }
 
         // Confirm
         let yes = Confirm::new("Apply this file?")
             .with_default(false)
             .prompt()?;
         if yes {
-            let backup_file = fsutil::backup_path(&backup_root, &abs, &root)?;
+            let backup_file = fsutil::backup_path(&backup_root, &abs, root)?;
             fsutil::backup_and_write(&abs, &new, &backup_file)?;
             if existed {
                 println!("Applied. Backup: {}", backup_file.display());
@@ -369,10 +1177,144 @@ fn apply_with_review(batch: edits::EditBatch, last_backups: &mut Vec<PathBuf>) -
                 println!("Created new file.");
                 last_backups.push(backup_file);
             }
+            applied.push(abs);
         } else {
             println!("Skipped {}", e.path);
         }
     }
 
+    if cfg.runtime.git_autocommit && !applied.is_empty() {
+        match git::autocommit(root, &applied, prompt) {
+            Ok(hash) => println!("Committed {hash}"),
+            Err(e) => println!("Warning: skipped git commit: {e:#}"),
+        }
+    }
+
     Ok(())
 }
+
+/// `--yes` counterpart of [`apply_with_review`]: prints each diff and writes
+/// it straight away.
+fn apply_without_review(
+    cfg: &config::AppConfig,
+    root: &Path,
+    prompt: &str,
+    prepared: &[edits::PreparedEdit],
+    last_backups: &mut fsutil::BackupStack,
+) -> Result<()> {
+    if prepared.is_empty() {
+        println!("No applicable edits.");
+        return Ok(());
+    }
+    for p in prepared {
+        println!("\n— Applying edit —");
+        println!("{}  {}", p.path, p.stats);
+        println!("────────────────────────────────────────────────");
+        println!("{}", p.unified_diff());
+        if let Some(r) = &p.rationale {
+            println!("Reason: {}", r);
+        }
+    }
+    let mut warnings = Vec::new();
+    let commit = apply_prepared(cfg, root, prompt, prepared, last_backups, &mut warnings)?;
+    for warning in warnings {
+        println!("Warning: {warning}");
+    }
+    println!("\nApplied {} file(s).", prepared.len());
+    if let Some(hash) = commit {
+        println!("Committed {hash}");
+    }
+    Ok(())
+}
+
+/// Writes every edit in `prepared` without asking, backing each file up
+/// first, then autocommits when enabled. Returns the commit hash, if any;
+/// anything worth flagging is added to `warnings`.
+fn apply_prepared(
+    cfg: &config::AppConfig,
+    root: &Path,
+    prompt: &str,
+    prepared: &[edits::PreparedEdit],
+    last_backups: &mut fsutil::BackupStack,
+    warnings: &mut Vec<String>,
+) -> Result<Option<String>> {
+    let backup_root = fsutil::timestamp_dir()?;
+    last_backups.begin_batch();
+    let mut applied = Vec::new();
+    for p in prepared {
+        if cfg.runtime.warn_dirty_files && git::is_dirty(root, Path::new(&p.path)) {
+            warnings.push(format!(
+                "{} had uncommitted changes that this edit overwrote.",
+                p.path
+            ));
+        }
+        let backup_file = fsutil::backup_path(&backup_root, &p.abs_path, root)?;
+        fsutil::backup_and_write(&p.abs_path, &p.new_contents, &backup_file)?;
+        last_backups.push(backup_file);
+        applied.push(p.abs_path.clone());
+    }
+
+    if !cfg.runtime.git_autocommit {
+        return Ok(None);
+    }
+    match git::autocommit(root, &applied, prompt) {
+        Ok(hash) => Ok(Some(hash)),
+        Err(e) => {
+            warnings.push(format!("skipped git commit: {e:#}"));
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reported_edits_carry_the_diff_and_whether_the_file_is_new() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.rs"), "let x = 1;\n").unwrap();
+        let batch: edits::EditBatch = serde_json::from_value(serde_json::json!({
+            "edits": [
+                {"path": "a.rs", "op": "replace", "anchor": "x = 1", "snippet": "x = 2"},
+                {"path": "b.rs", "op": "create_file", "snippet": "new\n", "rationale": "add b"}
+            ]
+        }))
+        .unwrap();
+        let prepared = edits::prepare_batch(&batch, dir.path(), true);
+        let reported: Vec<ReportedEdit> = prepared.iter().map(ReportedEdit::from).collect();
+        let json = serde_json::to_value(&reported).unwrap();
+
+        assert_eq!(json[0]["path"], "a.rs");
+        assert_eq!(json[0]["created"], false);
+        assert_eq!(json[0]["stats"], "+1 / -1");
+        let diff = json[0]["diff"].as_str().unwrap();
+        assert!(diff.contains("-let x = 1;") && diff.contains("+let x = 2;"));
+        assert_eq!(json[1]["created"], true);
+        assert_eq!(json[1]["rationale"], "add b");
+    }
+
+    #[test]
+    fn piped_one_shot_turns_need_yes_or_dry_run() {
+        let err = check_one_shot(false, false, false, Output::Text).unwrap_err();
+        assert!(err.to_string().contains("--yes"), "{err}");
+        assert!(check_one_shot(false, true, false, Output::Text).is_ok());
+        assert!(check_one_shot(false, false, true, Output::Text).is_ok());
+        assert!(check_one_shot(false, false, false, Output::Json).is_ok());
+        assert!(check_one_shot(true, false, false, Output::Text).is_ok());
+    }
+
+    #[test]
+    fn read_outcomes_serialize_with_a_status_tag() {
+        let log = agent::ReadLog {
+            path: "a.rs".into(),
+            outcome: agent::ReadOutcome::Failed {
+                error: "missing".into(),
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(&log).unwrap(),
+            serde_json::json!({"path": "a.rs", "outcome": {"status": "failed", "error": "missing"}})
+        );
+    }
+}
@@ -1,8 +1,288 @@
-use similar::TextDiff;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use similar::{ChangeTag, DiffTag, TextDiff};
+
+/// Lines added and removed between two versions of a file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineStats {
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+impl LineStats {
+    pub fn between(old: &str, new: &str) -> Self {
+        let mut stats = LineStats::default();
+        for change in TextDiff::from_lines(old, new).iter_all_changes() {
+            match change.tag() {
+                ChangeTag::Insert => stats.insertions += 1,
+                ChangeTag::Delete => stats.deletions += 1,
+                ChangeTag::Equal => {}
+            }
+        }
+        stats
+    }
+}
+
+impl std::fmt::Display for LineStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "+{} / -{}", self.insertions, self.deletions)
+    }
+}
 
 pub fn unified_diff(old: &str, new: &str, path: &str) -> String {
-    let diff = TextDiff::from_lines(old, new);
     let a_path = format!("a/{}", path);
     let b_path = format!("b/{}", path);
-    diff.unified_diff().header(&a_path, &b_path).to_string()
+    labeled_diff(old, new, &a_path, &b_path)
+}
+
+/// Unified diff with explicit `---`/`+++` labels, e.g. `/dev/null` for new files.
+pub fn labeled_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .header(old_label, new_label)
+        .to_string()
+}
+
+/// Styled variant of [`unified_diff`] for the TUI: additions green, removals red,
+/// hunk headers dimmed.
+pub fn unified_diff_spans(old: &str, new: &str, path: &str) -> Vec<Line<'static>> {
+    // Only the two leading lines are `---`/`+++` file headers; a removed
+    // `-- comment` or an added `++i` further down is an ordinary change.
+    unified_diff(old, new, path)
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| {
+            if idx < 2 {
+                header_line(line)
+            } else {
+                style_diff_line(line)
+            }
+        })
+        .collect()
+}
+
+/// Like [`unified_diff_spans`], but lines that were modified in place only
+/// emphasize the words that changed. Wholly added or removed lines keep the
+/// full-line styling.
+pub fn inline_diff(old: &str, new: &str, path: &str) -> Vec<Line<'static>> {
+    if old.is_empty() || new.is_empty() {
+        return unified_diff_spans(old, new, path);
+    }
+
+    let diff = TextDiff::from_lines(old, new);
+    let mut lines = vec![
+        header_line(&format!("--- a/{path}")),
+        header_line(&format!("+++ b/{path}")),
+    ];
+    for hunk in diff.unified_diff().iter_hunks() {
+        lines.push(style_diff_line(&hunk.header().to_string()));
+        for op in hunk.ops() {
+            for change in diff.iter_inline_changes(op) {
+                let (sign, base) = match change.tag() {
+                    ChangeTag::Delete => ("-", Style::default().fg(Color::Red)),
+                    ChangeTag::Insert => ("+", Style::default().fg(Color::Green)),
+                    ChangeTag::Equal => (" ", Style::default()),
+                };
+                let mut spans = vec![Span::styled(sign, base)];
+                for (emphasized, value) in change.iter_strings_lossy() {
+                    let text = value.trim_end_matches(['\n', '\r']).to_string();
+                    if text.is_empty() {
+                        continue;
+                    }
+                    let style = if emphasized {
+                        base.add_modifier(Modifier::BOLD | Modifier::REVERSED)
+                    } else {
+                        base
+                    };
+                    spans.push(Span::styled(text, style));
+                }
+                lines.push(Line::from(spans));
+            }
+        }
+    }
+    lines
+}
+
+/// Two-column diff, old on the left and new on the right, `width` characters
+/// wide in total. Hunks are separated by a dimmed header; each side carries
+/// its own line numbers and is truncated to fit its column.
+pub fn side_by_side(old: &str, new: &str, width: usize) -> Vec<Line<'static>> {
+    let diff = TextDiff::from_lines(old, new);
+    let old_lines = diff.old_slices();
+    let new_lines = diff.new_slices();
+    let col = width.saturating_sub(3) / 2;
+    let dim = Style::default().add_modifier(Modifier::DIM);
+    let mut lines = Vec::new();
+
+    for group in diff.grouped_ops(3) {
+        if let Some(first) = group.first() {
+            lines.push(Line::styled(
+                format!(
+                    "@@ -{} +{} @@",
+                    first.old_range().start + 1,
+                    first.new_range().start + 1
+                ),
+                dim,
+            ));
+        }
+        for op in &group {
+            let (tag, old_range, new_range) = op.as_tag_tuple();
+            let (left_style, right_style) = match tag {
+                DiffTag::Equal => (Style::default(), Style::default()),
+                DiffTag::Delete | DiffTag::Insert | DiffTag::Replace => (
+                    Style::default().fg(Color::Red),
+                    Style::default().fg(Color::Green),
+                ),
+            };
+            for i in 0..old_range.len().max(new_range.len()) {
+                let left = old_range.clone().nth(i).map(|n| (n, old_lines[n]));
+                let right = new_range.clone().nth(i).map(|n| (n, new_lines[n]));
+                lines.push(Line::from(vec![
+                    column_cell(left, col, left_style),
+                    Span::styled(" │ ", dim),
+                    column_cell(right, col, right_style),
+                ]));
+            }
+        }
+    }
+    lines
+}
+
+fn column_cell(line: Option<(usize, &str)>, width: usize, style: Style) -> Span<'static> {
+    let text = match line {
+        Some((idx, text)) => format!(
+            "{:>4} {}",
+            idx + 1,
+            text.trim_end_matches(['\n', '\r']).replace('\t', "    ")
+        ),
+        None => String::new(),
+    };
+    let text: String = text.chars().take(width).collect();
+    Span::styled(format!("{text:<width$}"), style)
+}
+
+fn header_line(line: &str) -> Line<'static> {
+    Line::styled(
+        line.to_string(),
+        Style::default().add_modifier(Modifier::BOLD),
+    )
+}
+
+/// Styles a hunk header or body line; file headers go through [`header_line`].
+fn style_diff_line(line: &str) -> Line<'static> {
+    let style = if line.starts_with("@@") {
+        Style::default().add_modifier(Modifier::DIM)
+    } else if line.starts_with('+') {
+        Style::default().fg(Color::Green)
+    } else if line.starts_with('-') {
+        Style::default().fg(Color::Red)
+    } else {
+        Style::default()
+    };
+    Line::styled(line.to_string(), style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line_fg(lines: &[Line<'static>], text: &str) -> Option<Color> {
+        lines
+            .iter()
+            .find(|l| l.to_string() == text)
+            .and_then(|l| l.style.fg)
+    }
+
+    #[test]
+    fn new_file_lines_are_green() {
+        let lines = unified_diff_spans("", "fn main() {}\n", "src/main.rs");
+        assert_eq!(line_fg(&lines, "+fn main() {}"), Some(Color::Green));
+        assert_eq!(line_fg(&lines, "+++ b/src/main.rs"), None);
+    }
+
+    #[test]
+    fn side_by_side_pairs_old_and_new_lines() {
+        let lines = side_by_side("a\nb\nc\n", "a\nB\nc\nd\n", 41);
+        let text: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        assert_eq!(text[0], "@@ -1 +1 @@");
+        assert!(
+            text.iter()
+                .all(|l| l.starts_with("@@") || l.chars().count() == 41)
+        );
+
+        let changed = lines
+            .iter()
+            .find(|l| l.to_string().contains("2 b"))
+            .unwrap();
+        assert!(changed.spans[2].content.contains("2 B"));
+        assert_eq!(changed.spans[0].style.fg, Some(Color::Red));
+        assert_eq!(changed.spans[2].style.fg, Some(Color::Green));
+
+        let added = text.iter().find(|l| l.contains("4 d")).unwrap();
+        assert!(added.starts_with(&" ".repeat(19)));
+    }
+
+    #[test]
+    fn unified_diff_emits_a_header_per_hunk() {
+        let old: String = (1..=20).map(|n| format!("{n}\n")).collect();
+        let new = old
+            .replacen("2\n", "two\n", 1)
+            .replacen("18\n", "eighteen\n", 1);
+        let headers: Vec<String> = unified_diff(&old, &new, "n.txt")
+            .lines()
+            .filter(|l| l.starts_with("@@"))
+            .map(str::to_string)
+            .collect();
+        assert_eq!(headers, vec!["@@ -1,5 +1,5 @@", "@@ -15,6 +15,6 @@"]);
+    }
+
+    #[test]
+    fn inline_diff_emphasizes_only_changed_words() {
+        let lines = inline_diff("let x = 1;\nkeep\n", "let x = 2;\nkeep\n", "a.rs");
+        let added = lines
+            .iter()
+            .find(|l| l.to_string() == "+let x = 2;")
+            .expect("added line");
+        let emphasized: Vec<String> = added
+            .spans
+            .iter()
+            .filter(|s| s.style.add_modifier.contains(Modifier::REVERSED))
+            .map(|s| s.content.to_string())
+            .collect();
+        assert_eq!(emphasized, vec!["2;"]);
+    }
+
+    #[test]
+    fn line_stats_count_insertions_and_deletions() {
+        let stats = LineStats::between("a\nb\nc\n", "a\nB\nc\nd\n");
+        assert_eq!(
+            stats,
+            LineStats {
+                insertions: 2,
+                deletions: 1
+            }
+        );
+        assert_eq!(stats.to_string(), "+2 / -1");
+    }
+
+    #[test]
+    fn deleted_lines_are_red() {
+        let lines = unified_diff_spans("fn main() {}\n", "", "src/main.rs");
+        assert_eq!(line_fg(&lines, "-fn main() {}"), Some(Color::Red));
+        assert_eq!(line_fg(&lines, "--- a/src/main.rs"), None);
+    }
+
+    #[test]
+    fn body_lines_that_look_like_file_headers_keep_their_change_color() {
+        let lines = unified_diff_spans("-- comment\ni\n", "i\n++i\n", "a.sql");
+        assert_eq!(line_fg(&lines, "--- comment"), Some(Color::Red));
+        assert_eq!(line_fg(&lines, "+++i"), Some(Color::Green));
+        let header = lines
+            .iter()
+            .find(|l| l.to_string() == "--- a/a.sql")
+            .unwrap();
+        assert!(header.style.add_modifier.contains(Modifier::BOLD));
+    }
 }
@@ -1,17 +1,40 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use crate::{diff, fsutil};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Edit {
     pub path: String,
-    pub op: String, // "replace" | "insert_after" | "insert_before"
+    pub op: String, // "replace" | "replace_regex" | "replace_lines" | "insert_after" | "insert_before" | "create_file" | "set_contents"
+    #[serde(default)]
     pub anchor: String,
     pub snippet: String,
     #[serde(default = "default_limit")]
     pub limit: usize,
+    /// With `limit == 1`, `replace` fails when the anchor occurs more than
+    /// once instead of editing the first match.
+    #[serde(default = "default_unique")]
+    pub unique: bool,
     #[serde(default)]
     pub rationale: Option<String>,
+    /// Allows `create_file` to replace an existing non-empty file.
+    #[serde(default)]
+    pub overwrite: bool,
+    /// 1-based, inclusive line range for `replace_lines`.
+    #[serde(default)]
+    pub start_line: Option<usize>,
+    #[serde(default)]
+    pub end_line: Option<usize>,
+    /// 1-based match of the anchor that `insert_after`/`insert_before` use.
+    #[serde(default = "default_occurrence")]
+    pub occurrence: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -23,6 +46,292 @@ fn default_limit() -> usize {
     1
 }
 
+fn default_unique() -> bool {
+    true
+}
+
+fn default_occurrence() -> usize {
+    1
+}
+
+/// An edit resolved against the file on disk, ready for review or export.
+#[derive(Debug, Clone)]
+pub struct PreparedEdit {
+    pub path: String,
+    pub abs_path: PathBuf,
+    pub rationale: Option<String>,
+    /// False when the edit creates the file.
+    pub existed: bool,
+    pub old_contents: String,
+    pub new_contents: String,
+    /// Kept up to date with the contents so the review header need not
+    /// re-diff on every frame.
+    pub stats: diff::LineStats,
+}
+
+impl PreparedEdit {
+    pub fn unified_diff(&self) -> String {
+        diff::unified_diff(&self.old_contents, &self.new_contents, &self.path)
+    }
+
+    /// Swaps in different proposed contents, e.g. after the user edited them.
+    pub fn set_new_contents(&mut self, new_contents: String) {
+        self.stats = diff::LineStats::between(&self.old_contents, &new_contents);
+        self.new_contents = new_contents;
+    }
+}
+
+/// Resolves every edit in `batch` against the disk without writing anything.
+/// Edits to the same file are applied on top of each other, matching what
+/// accepting all of them would produce. `normalize` applies
+/// [`normalize_new_file`].
+pub fn prepare_batch(batch: &EditBatch, root: &Path, normalize: bool) -> Vec<PreparedEdit> {
+    let mut prepared = resolve_batch(batch, root, normalize).0;
+    for p in &mut prepared {
+        p.stats = diff::LineStats::between(&p.old_contents, &p.new_contents);
+    }
+    prepared
+}
+
+/// How many edits in `batch` would change a file, ignoring blocked paths,
+/// edits that fail to apply and no-ops. Compared against
+/// `runtime.max_edits_per_batch`.
+pub fn effective_edit_count(batch: &EditBatch, root: &Path) -> usize {
+    resolve_batch(batch, root, false).1
+}
+
+/// One line per file in `batch`, in the order first touched, naming its ops:
+/// `src/main.rs: replace, insert_after`.
+pub fn batch_summary(batch: &EditBatch) -> Vec<String> {
+    let mut files: Vec<(&str, Vec<&str>)> = Vec::new();
+    for e in &batch.edits {
+        match files.iter_mut().find(|(path, _)| *path == e.path) {
+            Some((_, ops)) => ops.push(&e.op),
+            None => files.push((&e.path, vec![&e.op])),
+        }
+    }
+    files
+        .into_iter()
+        .map(|(path, ops)| format!("{path}: {}", ops.join(", ")))
+        .collect()
+}
+
+/// Drops exact duplicate edits silently, then skips any edit whose target
+/// overlaps text that an earlier edit to the same file replaces (or splits
+/// with an insertion), since applying the earlier one would leave the later
+/// anchor dangling. Returns a warning for each skipped overlap.
+pub fn dedupe_batch(batch: &mut EditBatch, root: &Path) -> Vec<String> {
+    let mut seen = Vec::new();
+    batch.edits.retain(|e| {
+        let key = (
+            e.path.clone(),
+            e.op.clone(),
+            e.anchor.clone(),
+            e.snippet.clone(),
+            e.start_line,
+            e.end_line,
+            e.occurrence,
+        );
+        if seen.contains(&key) {
+            return false;
+        }
+        seen.push(key);
+        true
+    });
+
+    let mut warnings = Vec::new();
+    let mut originals: Vec<(String, Option<String>)> = Vec::new();
+    let mut claimed: Vec<(String, Target)> = Vec::new();
+    batch.edits.retain(|e| {
+        let original = match originals.iter().find(|(path, _)| *path == e.path) {
+            Some((_, contents)) => contents.clone(),
+            None => {
+                let contents = fsutil::ensure_inside_repo(root, Path::new(&e.path))
+                    .ok()
+                    .and_then(|abs| fs::read_to_string(abs).ok());
+                originals.push((e.path.clone(), contents.clone()));
+                contents
+            }
+        };
+        let Some(target) = original.as_deref().and_then(|s| target(s, e)) else {
+            return true;
+        };
+        let needs = &target.needs;
+        let conflict = claimed
+            .iter()
+            .filter(|(path, _)| *path == e.path)
+            .any(|(_, earlier)| match earlier.inserts_at {
+                Some(at) => needs.start < at && at < needs.end,
+                None => earlier.needs.start < needs.end && needs.start < earlier.needs.end,
+            });
+        if conflict {
+            let line = original.as_deref().map_or(0, |s| line_of(s, needs.start));
+            warnings.push(format!(
+                "Skipped an edit to {} at line {line}: it overlaps an earlier edit in the same batch.",
+                e.path
+            ));
+            return false;
+        }
+        claimed.push((e.path.clone(), target));
+        true
+    });
+    warnings
+}
+
+/// Where an edit lands in the original file, for [`dedupe_batch`].
+struct Target {
+    /// Bytes that must still be intact when the edit is applied: the anchor,
+    /// or the lines being replaced.
+    needs: Range<usize>,
+    /// For insertions, the offset the snippet goes in at; replacements
+    /// rewrite all of `needs` instead.
+    inserts_at: Option<usize>,
+}
+
+/// `None` for whole-file and regex edits, and for anchors the original does
+/// not contain.
+fn target(original: &str, e: &Edit) -> Option<Target> {
+    let (needs, inserts_at) = match e.op.as_str() {
+        "replace" => (find_anchor(original, &e.anchor).ok()?, None),
+        "insert_after" => {
+            let range = find_occurrence(original, &e.anchor, e.occurrence).ok()?;
+            let at = range.end;
+            (range, Some(at))
+        }
+        "insert_before" => {
+            let range = find_occurrence(original, &e.anchor, e.occurrence).ok()?;
+            let at = range.start;
+            (range, Some(at))
+        }
+        "replace_lines" => {
+            let (start, end) = (e.start_line?, e.end_line?);
+            let lines: Vec<&str> = original.split_inclusive('\n').collect();
+            if start == 0 || end < start || end > lines.len() {
+                return None;
+            }
+            let from: usize = lines[..start - 1].iter().map(|l| l.len()).sum();
+            let len: usize = lines[start - 1..end].iter().map(|l| l.len()).sum();
+            (from..from + len, None)
+        }
+        _ => return None,
+    };
+    Some(Target { needs, inserts_at })
+}
+
+fn line_of(s: &str, offset: usize) -> usize {
+    s[..offset].matches('\n').count() + 1
+}
+
+fn resolve_batch(batch: &EditBatch, root: &Path, normalize: bool) -> (Vec<PreparedEdit>, usize) {
+    let mut prepared: Vec<PreparedEdit> = Vec::new();
+    let mut changing = 0;
+    for e in &batch.edits {
+        if fsutil::is_write_blocked(&e.path) {
+            continue;
+        }
+        let Ok(abs) = fsutil::ensure_inside_repo(root, Path::new(&e.path)) else {
+            continue;
+        };
+        let idx = match prepared.iter().position(|p| p.path == e.path) {
+            Some(idx) => idx,
+            None => {
+                let (old, existed) = match fs::read_to_string(&abs) {
+                    Ok(contents) => (contents, true),
+                    Err(_) => (String::new(), false),
+                };
+                prepared.push(PreparedEdit {
+                    path: e.path.clone(),
+                    abs_path: abs,
+                    rationale: e.rationale.clone(),
+                    existed,
+                    new_contents: old.clone(),
+                    old_contents: old,
+                    stats: diff::LineStats::default(),
+                });
+                prepared.len() - 1
+            }
+        };
+        if let Ok(new) = apply_edit(&prepared[idx].new_contents, e)
+            .map(|new| normalize_new_file(&prepared[idx].old_contents, new, normalize))
+            && new != prepared[idx].new_contents
+        {
+            prepared[idx].new_contents = new;
+            changing += 1;
+        }
+    }
+    prepared.retain(|p| p.old_contents != p.new_contents);
+    (prepared, changing)
+}
+
+/// With `runtime.normalize_newlines`, gives text written into a new or empty
+/// file LF line endings and exactly one trailing newline. Edits to files that
+/// already have contents, and content that looks binary, are left alone.
+pub fn normalize_new_file(old: &str, new: String, enabled: bool) -> String {
+    if !enabled || !old.is_empty() || new.is_empty() || fsutil::looks_binary(new.as_bytes()) {
+        return new;
+    }
+    let mut text = new.replace("\r\n", "\n");
+    let end = text.trim_end_matches('\n').len();
+    text.truncate(end);
+    text.push('\n');
+    text
+}
+
+/// Concatenates the unified diffs of `edits` into a single patch.
+pub fn combined_diff(edits: &[PreparedEdit]) -> String {
+    edits.iter().map(PreparedEdit::unified_diff).collect()
+}
+
+/// Renders `edits` as a patch `git apply` accepts. Several edits to the same
+/// file collapse into one diff from its first to its last contents, and new
+/// files are diffed against `/dev/null`.
+pub fn to_git_patch(edits: &[PreparedEdit]) -> String {
+    let mut files: Vec<(&str, bool, &str, &str)> = Vec::new();
+    for edit in edits {
+        let path = edit.path.trim_start_matches("./");
+        match files.iter_mut().find(|(p, ..)| *p == path) {
+            Some(file) => file.3 = &edit.new_contents,
+            None => files.push((path, edit.existed, &edit.old_contents, &edit.new_contents)),
+        }
+    }
+
+    let mut out = String::new();
+    for (path, existed, old, new) in files {
+        if old == new {
+            continue;
+        }
+        out.push_str(&format!("diff --git a/{path} b/{path}\n"));
+        let old_label = if existed {
+            format!("a/{path}")
+        } else {
+            out.push_str("new file mode 100644\n");
+            "/dev/null".to_string()
+        };
+        out.push_str(&diff::labeled_diff(
+            old,
+            new,
+            &old_label,
+            &format!("b/{path}"),
+        ));
+    }
+    out
+}
+
+/// Writes [`to_git_patch`] output to `file`, which must resolve inside the repo
+/// and pass the same write guard as edits.
+pub fn export_patch(repo_root: &Path, file: &str, edits: &[PreparedEdit]) -> Result<PathBuf> {
+    if fsutil::is_write_blocked(file) {
+        anyhow::bail!("refusing to write {file}");
+    }
+    let abs = fsutil::ensure_inside_repo(repo_root, Path::new(file))
+        .with_context(|| format!("invalid path {file}"))?;
+    if let Some(parent) = abs.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+    }
+    fs::write(&abs, to_git_patch(edits)).with_context(|| format!("write {}", abs.display()))?;
+    Ok(abs)
+}
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub enum Action {
@@ -33,16 +342,27 @@ pub enum Action {
 }
 
 pub fn parse_actions(json_text: &str) -> Result<Vec<Action>> {
-    let tool_calls: Vec<serde_json::Value> =
-        serde_json::from_str(json_text).context("failed to parse tool calls")?;
+    let tool_calls = match wrapped_tool_calls(json_text) {
+        Some(tool_calls) => tool_calls,
+        None => {
+            let json_text = extract_json_array(json_text).unwrap_or(json_text);
+            serde_json::from_str::<Vec<serde_json::Value>>(json_text)
+                .context("failed to parse tool calls")?
+        }
+    };
 
     let actions = tool_calls
         .into_iter()
         .filter_map(|call| {
             let function = call.get("function")?;
             let name = function.get("name")?.as_str()?;
-            let args: serde_json::Value =
-                serde_json::from_str(function.get("arguments")?.as_str()?).ok()?;
+            // Native tool calls carry arguments as a JSON string; models
+            // writing calls as text often inline the object instead.
+            let args = match function.get("arguments")? {
+                serde_json::Value::String(raw) => serde_json::from_str(raw).ok()?,
+                inline @ serde_json::Value::Object(_) => inline.clone(),
+                _ => return None,
+            };
 
             match name {
                 "read" => {
@@ -57,22 +377,7 @@ pub fn parse_actions(json_text: &str) -> Result<Vec<Action>> {
                         .to_string();
                     Some(Action::ListDirectory { path })
                 }
-                "edit" => {
-                    let path = args.get("file_path")?.as_str()?.to_string();
-                    let old_string = args.get("old_string")?.as_str()?.to_string();
-                    let new_string = args.get("new_string")?.as_str()?.to_string();
-                    let mut edit = Edit {
-                        path,
-                        op: "replace".to_string(),
-                        anchor: old_string,
-                        snippet: new_string,
-                        limit: 1,
-                        rationale: None,
-                    };
-                    normalize_html(&mut edit);
-                    Some(Action::Edit(edit))
-                }
-                _ => None,
+                other => edit_from_tool_call(other, &args).map(Action::Edit),
             }
         })
         .collect();
@@ -80,6 +385,68 @@ pub fn parse_actions(json_text: &str) -> Result<Vec<Action>> {
     Ok(actions)
 }
 
+/// Maps one edit tool call (`edit`, `replace_regex`, `replace_lines`,
+/// `insert_after`, `insert_before`, `create_file`, `set_contents`) to an
+/// [`Edit`]. `None` for other tools or missing arguments.
+pub fn edit_from_tool_call(name: &str, args: &serde_json::Value) -> Option<Edit> {
+    let str_arg = |key: &str| Some(args.get(key)?.as_str()?.to_string());
+    let line_arg = |key: &str| Some(args.get(key)?.as_u64()? as usize);
+    let path = str_arg("file_path")?;
+    let mut edit = Edit {
+        path,
+        op: String::new(),
+        anchor: String::new(),
+        snippet: String::new(),
+        limit: 1,
+        unique: true,
+        rationale: None,
+        overwrite: false,
+        start_line: None,
+        end_line: None,
+        occurrence: 1,
+    };
+    match name {
+        "edit" => {
+            edit.op = "replace".to_string();
+            edit.anchor = str_arg("old_string")?;
+            edit.snippet = str_arg("new_string")?;
+        }
+        "replace_regex" => {
+            edit.op = "replace_regex".to_string();
+            edit.anchor = str_arg("pattern")?;
+            edit.snippet = str_arg("replacement")?;
+            edit.limit = line_arg("limit").unwrap_or(1);
+        }
+        "replace_lines" => {
+            edit.op = "replace_lines".to_string();
+            edit.start_line = Some(line_arg("start_line")?);
+            edit.end_line = Some(line_arg("end_line")?);
+            edit.snippet = str_arg("new_string")?;
+        }
+        "insert_after" | "insert_before" => {
+            edit.op = name.to_string();
+            edit.anchor = str_arg("anchor")?;
+            edit.snippet = str_arg("new_string")?;
+            edit.occurrence = line_arg("occurrence").unwrap_or(1);
+        }
+        "create_file" => {
+            edit.op = "create_file".to_string();
+            edit.snippet = str_arg("content")?;
+            edit.overwrite = args
+                .get("overwrite")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+        }
+        "set_contents" => {
+            edit.op = "set_contents".to_string();
+            edit.snippet = str_arg("content")?;
+        }
+        _ => return None,
+    }
+    normalize_html(&mut edit);
+    Some(edit)
+}
+
 fn normalize_html(edit: &mut Edit) {
     if !is_html_path(&edit.path) {
         return;
@@ -111,6 +478,81 @@ fn html_unescape(input: &str) -> Cow<'_, str> {
     Cow::Owned(s)
 }
 
+/// Finds the tool-call array in model output that wraps it in ```json fences or
+/// surrounds it with prose: the first balanced `[...]` that parses as JSON.
+pub fn extract_json_array(text: &str) -> Option<&str> {
+    let body = strip_code_fence(text).unwrap_or(text);
+    for (start, _) in body.match_indices('[') {
+        if let Some(len) = balanced_len(&body[start..]) {
+            let candidate = &body[start..start + len];
+            if serde_json::from_str::<Vec<serde_json::Value>>(candidate).is_ok() {
+                return Some(candidate);
+            }
+        }
+    }
+    None
+}
+
+/// The tool-call array from a reply that wraps it in an object, as
+/// `{"edits": [...]}` or `{"tool_calls": [...]}`, instead of sending it bare.
+fn wrapped_tool_calls(text: &str) -> Option<Vec<serde_json::Value>> {
+    let body = strip_code_fence(text).unwrap_or(text);
+    let start = body.find('{')?;
+    // An object inside a bare array is a tool call, not a wrapper.
+    if body.find('[').is_some_and(|bracket| bracket < start) {
+        return None;
+    }
+    let len = balanced_len(&body[start..])?;
+    let mut wrapper: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(&body[start..start + len]).ok()?;
+    ["edits", "tool_calls"]
+        .into_iter()
+        .find_map(|key| match wrapper.remove(key)? {
+            serde_json::Value::Array(tool_calls) => Some(tool_calls),
+            _ => None,
+        })
+}
+
+fn strip_code_fence(text: &str) -> Option<&str> {
+    let open = text.find("```")?;
+    let after = &text[open + 3..];
+    let body_start = after.find('\n')? + 1;
+    let body = &after[body_start..];
+    let close = body.find("```")?;
+    Some(&body[..close])
+}
+
+/// Byte length of the bracketed value starting at `s[0]`, ignoring brackets
+/// inside JSON strings.
+fn balanced_len(s: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (idx, ch) in s.char_indices() {
+        if in_string {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match ch {
+            '"' => in_string = true,
+            '[' | '{' => depth += 1,
+            ']' | '}' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(idx + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
 pub fn parse_edits(json_text: &str) -> Result<EditBatch> {
     let actions = parse_actions(json_text)?;
     let edits = actions
@@ -126,15 +568,35 @@ pub fn parse_edits(json_text: &str) -> Result<EditBatch> {
 
 pub fn apply_edit(original: &str, e: &Edit) -> Result<String> {
     match e.op.as_str() {
-        "replace" => replace_once(original, &e.anchor, &e.snippet, e.limit),
-        "insert_after" => insert_after(original, &e.anchor, &e.snippet),
-        "insert_before" => insert_before(original, &e.anchor, &e.snippet),
+        "replace" => replace_once(original, &e.anchor, &e.snippet, e.limit, e.unique),
+        "replace_regex" => replace_regex(original, &e.anchor, &e.snippet, e.limit),
+        "replace_lines" => replace_lines(original, e.start_line, e.end_line, &e.snippet),
+        "insert_after" => insert_after(original, &e.anchor, &e.snippet, e.occurrence),
+        "insert_before" => insert_before(original, &e.anchor, &e.snippet, e.occurrence),
+        "create_file" => create_file(original, &e.snippet, e.overwrite),
+        "set_contents" => Ok(e.snippet.clone()),
         other => Err(anyhow::anyhow!("unsupported op: {other}")),
     }
 }
 
-fn replace_once(s: &str, anchor: &str, snippet: &str, limit: usize) -> Result<String> {
+fn replace_once(
+    s: &str,
+    anchor: &str,
+    snippet: &str,
+    limit: usize,
+    unique: bool,
+) -> Result<String> {
     let count = s.matches(anchor).count();
+    if count == 0 {
+        let range = find_normalized(s, anchor)?;
+        let snippet = match_trailing_newline(&s[range.clone()], snippet);
+        return Ok(splice(s, range, &snippet));
+    }
+    if unique && limit == 1 && count > 1 {
+        anyhow::bail!(
+            "anchor matches {count} times; include more surrounding lines so it matches exactly once"
+        );
+    }
     if count < limit {
         anyhow::bail!("anchor not found enough times");
     }
@@ -142,30 +604,669 @@ fn replace_once(s: &str, anchor: &str, snippet: &str, limit: usize) -> Result<St
     let mut parts = s.splitn(2, anchor);
     let head = parts.next().unwrap_or("");
     let tail = parts.next().unwrap_or("");
+    let snippet = match_trailing_newline(anchor, snippet);
     Ok(format!("{head}{snippet}{tail}"))
 }
 
-fn insert_after(s: &str, anchor: &str, snippet: &str) -> Result<String> {
-    if let Some(idx) = s.find(anchor) {
-        let insert_at = idx + anchor.len();
-        let mut out = String::with_capacity(s.len() + snippet.len());
-        out.push_str(&s[..insert_at]);
-        out.push_str(snippet);
-        out.push_str(&s[insert_at..]);
-        Ok(out)
+/// `snippet` with its final line break made to match the text it replaces:
+/// one is added when the replaced text ended with a line break and dropped
+/// when it did not, so the edit neither joins the next line on nor leaves a
+/// stray blank line or end-of-file newline change. An empty snippet deletes
+/// and is left as is.
+fn match_trailing_newline<'a>(replaced: &str, snippet: &'a str) -> Cow<'a, str> {
+    let ending = if replaced.ends_with("\r\n") {
+        "\r\n"
+    } else if replaced.ends_with('\n') {
+        "\n"
     } else {
-        anyhow::bail!("anchor not found");
+        ""
+    };
+    if snippet.is_empty() {
+        Cow::Borrowed(snippet)
+    } else if !ending.is_empty() && !snippet.ends_with('\n') {
+        Cow::Owned(format!("{snippet}{ending}"))
+    } else if ending.is_empty()
+        && let Some(trimmed) = snippet.strip_suffix('\n')
+    {
+        Cow::Borrowed(trimmed.strip_suffix('\r').unwrap_or(trimmed))
+    } else {
+        Cow::Borrowed(snippet)
+    }
+}
+
+/// Compiled-size cap for `replace_regex` patterns, so a pathological pattern is
+/// rejected instead of blowing up memory.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Replaces up to `limit` matches of the `pattern` regex with `replacement`,
+/// which may reference capture groups as `$1` or `${name}`.
+fn replace_regex(s: &str, pattern: &str, replacement: &str, limit: usize) -> Result<String> {
+    let re = regex::RegexBuilder::new(pattern)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .with_context(|| format!("invalid regex {pattern:?}"))?;
+    if !re.is_match(s) {
+        anyhow::bail!("regex {pattern:?} did not match");
+    }
+    Ok(re.replacen(s, limit.max(1), replacement).into_owned())
+}
+
+/// Replaces lines `start..=end` (1-based) with `snippet`. An empty snippet
+/// deletes them. The snippet's final line break follows the replaced lines',
+/// as in [`match_trailing_newline`].
+fn replace_lines(
+    s: &str,
+    start: Option<usize>,
+    end: Option<usize>,
+    snippet: &str,
+) -> Result<String> {
+    let (Some(start), Some(end)) = (start, end) else {
+        anyhow::bail!("replace_lines needs start_line and end_line");
+    };
+    let lines: Vec<&str> = s.split_inclusive('\n').collect();
+    if start == 0 || end < start || end > lines.len() {
+        anyhow::bail!(
+            "line range {start}-{end} is out of bounds; the file has {} lines",
+            lines.len()
+        );
+    }
+    let mut out = String::with_capacity(s.len() + snippet.len());
+    out.extend(lines[..start - 1].iter().copied());
+    out.push_str(&match_trailing_newline(lines[end - 1], snippet));
+    out.extend(lines[end..].iter().copied());
+    Ok(out)
+}
+
+fn insert_after(s: &str, anchor: &str, snippet: &str, occurrence: usize) -> Result<String> {
+    let range = find_occurrence(s, anchor, occurrence)?;
+    Ok(splice(s, range.end..range.end, snippet))
+}
+
+fn insert_before(s: &str, anchor: &str, snippet: &str, occurrence: usize) -> Result<String> {
+    let range = find_occurrence(s, anchor, occurrence)?;
+    Ok(splice(s, range.start..range.start, snippet))
+}
+
+/// Byte range of the `occurrence`-th (1-based) exact match of `anchor`. The
+/// first occurrence also accepts a whitespace-tolerant match, like
+/// [`find_anchor`].
+fn find_occurrence(s: &str, anchor: &str, occurrence: usize) -> Result<Range<usize>> {
+    match occurrence {
+        0 => anyhow::bail!("occurrence is 1-based"),
+        1 => find_anchor(s, anchor),
+        n => {
+            let count = s.matches(anchor).count();
+            let Some((idx, _)) = s.match_indices(anchor).nth(n - 1) else {
+                anyhow::bail!("occurrence {n} requested but the anchor matches {count} times");
+            };
+            Ok(idx..idx + anchor.len())
+        }
+    }
+}
+
+fn splice(s: &str, range: Range<usize>, snippet: &str) -> String {
+    let mut out = String::with_capacity(s.len() + snippet.len());
+    out.push_str(&s[..range.start]);
+    out.push_str(snippet);
+    out.push_str(&s[range.end..]);
+    out
+}
+
+/// Byte range of the first exact match, falling back to a whitespace-tolerant
+/// search when the anchor does not appear verbatim.
+fn find_anchor(s: &str, anchor: &str) -> Result<Range<usize>> {
+    match s.find(anchor) {
+        Some(idx) => Ok(idx..idx + anchor.len()),
+        None => find_normalized(s, anchor),
     }
 }
 
-fn insert_before(s: &str, anchor: &str, snippet: &str) -> Result<String> {
-    if let Some(idx) = s.find(anchor) {
-        let mut out = String::with_capacity(s.len() + snippet.len());
-        out.push_str(&s[..idx]);
-        out.push_str(snippet);
-        out.push_str(&s[idx..]);
-        Ok(out)
+/// Matches the anchor line-by-line after trimming each line and collapsing
+/// internal whitespace runs. The returned range spans from the first
+/// non-whitespace byte of the first matched line to the last non-whitespace byte
+/// of the last one, so surrounding indentation in the file is preserved.
+fn find_normalized(s: &str, anchor: &str) -> Result<Range<usize>> {
+    let wanted: Vec<String> = anchor.lines().map(normalize_ws).collect();
+    let first = wanted.iter().position(|l| !l.is_empty());
+    let last = wanted.iter().rposition(|l| !l.is_empty());
+    let wanted = match (first, last) {
+        (Some(first), Some(last)) => &wanted[first..=last],
+        _ => anyhow::bail!("anchor not found"),
+    };
+
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for line in s.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        lines.push((offset, content, normalize_ws(content)));
+        offset += line.len();
+    }
+
+    let matches: Vec<usize> = (0..lines.len())
+        .filter(|&i| {
+            i + wanted.len() <= lines.len()
+                && wanted
+                    .iter()
+                    .zip(&lines[i..i + wanted.len()])
+                    .all(|(w, (_, _, norm))| w == norm)
+        })
+        .collect();
+
+    match matches.as_slice() {
+        [] => anyhow::bail!(
+            "anchor not found; {}",
+            anchor_diagnostics(&lines, wanted, anchor)
+        ),
+        [i] => {
+            let (first_off, first_line, _) = lines[*i];
+            let (last_off, last_line, _) = lines[*i + wanted.len() - 1];
+            let start = first_off + (first_line.len() - first_line.trim_start().len());
+            let end = last_off + last_line.trim_end().len();
+            Ok(start..end)
+        }
+        many => anyhow::bail!(
+            "anchor matches {} locations after whitespace normalization; provide a more specific anchor",
+            many.len()
+        ),
+    }
+}
+
+/// Anchors whose closest window scores below this are reported as having no
+/// near match rather than pointing at an unrelated line.
+const MIN_SUGGESTION_SIMILARITY: f32 = 0.5;
+
+/// Characters of the anchor shown from each end in a not-found error.
+const ANCHOR_PREVIEW_CHARS: usize = 40;
+
+/// Describes why an anchor failed to match: the window of file lines most
+/// similar to it (after the same whitespace normalization) and both ends of
+/// the anchor, quoted so stray whitespace or typos are visible.
+fn anchor_diagnostics(lines: &[(usize, &str, String)], wanted: &[String], anchor: &str) -> String {
+    let target = wanted.join("\n");
+    let closest = (0..lines.len())
+        .filter(|&i| i + wanted.len() <= lines.len())
+        .map(|i| {
+            let window: Vec<&str> = lines[i..i + wanted.len()]
+                .iter()
+                .map(|(_, _, norm)| norm.as_str())
+                .collect();
+            let ratio =
+                similar::TextDiff::from_chars(target.as_str(), window.join("\n").as_str()).ratio();
+            (i, ratio)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+
+    let chars: Vec<char> = anchor.chars().collect();
+    let ends = if chars.len() <= ANCHOR_PREVIEW_CHARS * 2 {
+        format!("anchor is {anchor:?}")
     } else {
-        anyhow::bail!("anchor not found");
+        let head: String = chars[..ANCHOR_PREVIEW_CHARS].iter().collect();
+        let tail: String = chars[chars.len() - ANCHOR_PREVIEW_CHARS..].iter().collect();
+        format!("anchor starts {head:?} and ends {tail:?}")
+    };
+    match closest {
+        Some((i, ratio)) if ratio >= MIN_SUGGESTION_SIMILARITY => format!(
+            "closest match is line {} ({:.0}% similar): {:?}; {ends}",
+            i + 1,
+            ratio * 100.0,
+            lines[i].1.trim()
+        ),
+        _ => format!("no similar lines in the file; {ends}"),
+    }
+}
+
+fn normalize_ws(line: &str) -> String {
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A `set_contents` edit that leaves less than this share of the original
+/// bytes is flagged as a possible accidental truncation.
+const SHRINK_WARN_RATIO: f64 = 0.2;
+
+/// A warning when `e` replaces a whole file with dramatically less content,
+/// e.g. a model that elided the parts it left unchanged.
+pub fn shrink_warning(e: &Edit, old: &str, new: &str) -> Option<String> {
+    if e.op != "set_contents" || old.is_empty() {
+        return None;
+    }
+    let ratio = new.len() as f64 / old.len() as f64;
+    (ratio < SHRINK_WARN_RATIO).then(|| {
+        format!(
+            "{} would shrink from {} to {} bytes ({:.0}%); check the new contents were not cut short",
+            e.path,
+            old.len(),
+            new.len(),
+            ratio * 100.0
+        )
+    })
+}
+
+fn create_file(existing: &str, contents: &str, overwrite: bool) -> Result<String> {
+    if !existing.is_empty() && !overwrite {
+        anyhow::bail!("file already exists and is not empty (set overwrite to replace it)");
+    }
+    Ok(contents.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(op: &str, anchor: &str, snippet: &str) -> Edit {
+        Edit {
+            path: "src/lib.rs".into(),
+            op: op.into(),
+            anchor: anchor.into(),
+            snippet: snippet.into(),
+            limit: 1,
+            unique: true,
+            rationale: None,
+            overwrite: false,
+            start_line: None,
+            end_line: None,
+            occurrence: 1,
+        }
+    }
+
+    fn prepared(path: &str, existed: bool, old: &str, new: &str) -> PreparedEdit {
+        PreparedEdit {
+            path: path.into(),
+            abs_path: PathBuf::from(path),
+            rationale: None,
+            existed,
+            old_contents: old.into(),
+            new_contents: new.into(),
+            stats: crate::diff::LineStats::between(old, new),
+        }
+    }
+
+    #[test]
+    fn missing_anchor_reports_the_closest_line() {
+        let file = "fn main() {\n    let total = compute(1, 2);\n    println!(\"{total}\");\n}\n";
+        let err = apply_edit(file, &edit("replace", "let totl = compute(1, 2);", "x"))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with("anchor not found; closest match is line 2"),
+            "{err}"
+        );
+        assert!(err.contains("\"let total = compute(1, 2);\""), "{err}");
+        assert!(
+            err.ends_with("anchor is \"let totl = compute(1, 2);\""),
+            "{err}"
+        );
+
+        let err = apply_edit(file, &edit("replace", &"zq".repeat(50), "x"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("no similar lines"), "{err}");
+        assert!(
+            err.contains(&format!("starts {:?}", "zq".repeat(20))),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn new_files_get_lf_endings_and_one_trailing_newline() {
+        assert_eq!(
+            normalize_new_file("", "fn a() {}\r\nfn b() {}".into(), true),
+            "fn a() {}\nfn b() {}\n"
+        );
+        assert_eq!(normalize_new_file("", "x\n\n\n".into(), true), "x\n");
+        assert_eq!(normalize_new_file("", "x\r\n".into(), false), "x\r\n");
+        assert_eq!(
+            normalize_new_file("old\r\n", "new\r\n".into(), true),
+            "new\r\n"
+        );
+        assert_eq!(normalize_new_file("", "a\0b".into(), true), "a\0b");
+    }
+
+    #[test]
+    fn inserts_target_the_requested_occurrence() {
+        let src = "x();\nx();\nx();\n";
+        let mut after = edit("insert_after", "x();\n", "y();\n");
+        after.occurrence = 2;
+        assert_eq!(apply_edit(src, &after).unwrap(), "x();\nx();\ny();\nx();\n");
+
+        let mut before = edit("insert_before", "x();", "y();\n");
+        before.occurrence = 3;
+        assert_eq!(
+            apply_edit(src, &before).unwrap(),
+            "x();\nx();\ny();\nx();\n"
+        );
+
+        before.occurrence = 4;
+        let err = apply_edit(src, &before).unwrap_err().to_string();
+        assert_eq!(err, "occurrence 4 requested but the anchor matches 3 times");
+    }
+
+    #[test]
+    fn git_patch_diffs_new_files_against_dev_null() {
+        let patch = to_git_patch(&[prepared("src/new.rs", false, "", "fn a() {}\n")]);
+        assert!(patch.starts_with(
+            "diff --git a/src/new.rs b/src/new.rs\nnew file mode 100644\n--- /dev/null\n+++ b/src/new.rs\n@@ -0,0 +1 @@\n"
+        ));
+    }
+
+    #[test]
+    fn git_patch_collapses_edits_to_the_same_file() {
+        let patch = to_git_patch(&[
+            prepared("a.rs", true, "one\n", "two\n"),
+            prepared("a.rs", true, "two\n", "three\n"),
+        ]);
+        assert_eq!(patch.matches("diff --git").count(), 1);
+        assert!(patch.contains("-one\n+three\n"));
+    }
+
+    #[test]
+    fn export_patch_refuses_blocked_paths_and_reports_dir_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::write(root.join(".git/config"), "[core]\n").unwrap();
+        fs::write(root.join("notes.txt"), "x\n").unwrap();
+        let edits = [prepared("a.rs", true, "one\n", "two\n")];
+
+        let err = export_patch(root, ".git/config", &edits).unwrap_err();
+        assert_eq!(err.to_string(), "refusing to write .git/config");
+        assert_eq!(
+            fs::read_to_string(root.join(".git/config")).unwrap(),
+            "[core]\n"
+        );
+
+        let err = export_patch(root, "notes.txt/out.patch", &edits).unwrap_err();
+        assert!(err.to_string().starts_with("create "), "{err}");
+
+        let written = export_patch(root, "patches/fix.patch", &edits).unwrap();
+        assert!(fs::read_to_string(written).unwrap().contains("+two\n"));
+    }
+
+    const CALLS: &str = r#"[{"id":"call_0","type":"function","function":{"name":"edit","arguments":"{\"file_path\":\"a.rs\",\"old_string\":\"x[0]\",\"new_string\":\"y\"}"}}]"#;
+
+    #[test]
+    fn parse_edits_accepts_fenced_json() {
+        let text = format!("```json\n{CALLS}\n```");
+        assert_eq!(parse_edits(&text).unwrap().edits.len(), 1);
+    }
+
+    #[test]
+    fn parse_edits_skips_leading_prose() {
+        let text = format!("Here are the edits [as requested]:\n{CALLS}");
+        let batch = parse_edits(&text).unwrap();
+        assert_eq!(batch.edits[0].anchor, "x[0]");
+    }
+
+    #[test]
+    fn parse_edits_ignores_trailing_explanation() {
+        let text = format!("{CALLS}\n\nThis renames x[0] to y.");
+        assert_eq!(parse_edits(&text).unwrap().edits.len(), 1);
+        assert!(parse_edits("no json here").is_err());
+    }
+
+    #[test]
+    fn create_file_writes_full_contents_into_empty_file() {
+        let e = edit("create_file", "ignored", "fn main() {}\n");
+        assert_eq!(apply_edit("", &e).unwrap(), "fn main() {}\n");
+    }
+
+    #[test]
+    fn create_file_refuses_to_clobber_without_overwrite() {
+        let mut e = edit("create_file", "", "new\n");
+        assert!(apply_edit("old\n", &e).is_err());
+        e.overwrite = true;
+        assert_eq!(apply_edit("old\n", &e).unwrap(), "new\n");
+    }
+
+    #[test]
+    fn set_contents_replaces_whole_file_and_flags_big_shrinks() {
+        let e = edit("set_contents", "", "short\n");
+        let old = "line\n".repeat(10);
+        assert_eq!(apply_edit(&old, &e).unwrap(), "short\n");
+        assert!(shrink_warning(&e, &old, "short\n").is_some());
+        assert_eq!(shrink_warning(&e, &old, &"line\n".repeat(5)), None);
+        assert_eq!(shrink_warning(&e, "", "short\n"), None);
+    }
+
+    #[test]
+    fn replace_tolerates_indentation_mismatch() {
+        let src = "fn main() {\n        let x = 1;\n}\n";
+        let e = edit("replace", "let  x = 1;", "let x = 2;");
+        assert_eq!(
+            apply_edit(src, &e).unwrap(),
+            "fn main() {\n        let x = 2;\n}\n"
+        );
+    }
+
+    #[test]
+    fn multiline_anchor_matches_with_different_indentation() {
+        let src = "impl A {\n    fn a() {\n        todo!()\n    }\n}\n";
+        let e = edit("insert_after", "fn a() {\n  todo!()\n}", "\n    fn b() {}");
+        assert_eq!(
+            apply_edit(src, &e).unwrap(),
+            "impl A {\n    fn a() {\n        todo!()\n    }\n    fn b() {}\n}\n"
+        );
+    }
+
+    #[test]
+    fn ambiguous_normalized_anchor_is_rejected() {
+        let src = "    x += 1;\n\tx += 1;\n";
+        let e = edit("insert_before", "x  += 1;", "y();\n");
+        let err = apply_edit(src, &e).unwrap_err().to_string();
+        assert!(err.contains("more specific anchor"), "{err}");
+    }
+
+    #[test]
+    fn replace_regex_substitutes_capture_groups() {
+        let src = "let foo_bar = foo_baz;\n";
+        let mut e = edit("replace_regex", r"foo_(\w+)", "foo${1}X");
+        e.limit = 10;
+        assert_eq!(apply_edit(src, &e).unwrap(), "let foobarX = foobazX;\n");
+    }
+
+    #[test]
+    fn replace_regex_respects_limit_and_rejects_bad_patterns() {
+        let src = "a a a\n";
+        let mut e = edit("replace_regex", "a", "b");
+        e.limit = 2;
+        assert_eq!(apply_edit(src, &e).unwrap(), "b b a\n");
+
+        let err = apply_edit(src, &edit("replace_regex", "(a", "b")).unwrap_err();
+        assert!(err.to_string().contains("invalid regex"), "{err}");
+        assert!(apply_edit(src, &edit("replace_regex", "z", "b")).is_err());
+    }
+
+    #[test]
+    fn replace_rejects_ambiguous_anchor_unless_permissive() {
+        let src = "x = 1;\nx = 1;\n";
+        let err = apply_edit(src, &edit("replace", "x = 1;", "x = 2;"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("matches 2 times"), "{err}");
+
+        let mut first_only = edit("replace", "x = 1;", "x = 2;");
+        first_only.unique = false;
+        assert_eq!(apply_edit(src, &first_only).unwrap(), "x = 2;\nx = 1;\n");
+    }
+
+    #[test]
+    fn exact_match_takes_precedence() {
+        let src = "a\n  b\n";
+        let e = edit("replace", "b", "c");
+        assert_eq!(apply_edit(src, &e).unwrap(), "a\n  c\n");
+    }
+
+    #[test]
+    fn replacing_a_line_keeps_its_trailing_newline() {
+        let src = "fn a() {}\nfn b() {}\n";
+        for snippet in ["fn c() {}", "fn c() {}\n"] {
+            let e = edit("replace", "fn a() {}\n", snippet);
+            assert_eq!(apply_edit(src, &e).unwrap(), "fn c() {}\nfn b() {}\n");
+        }
+        for snippet in ["fn c() {}", "fn c() {}\n"] {
+            let e = edit("replace", "fn b() {}", snippet);
+            assert_eq!(
+                apply_edit("fn a() {}\nfn b() {}", &e).unwrap(),
+                "fn a() {}\nfn c() {}"
+            );
+        }
+
+        let crlf = edit("replace", "a\r\n", "c");
+        assert_eq!(apply_edit("a\r\nb\r\n", &crlf).unwrap(), "c\r\nb\r\n");
+        let indented = edit("replace", "let x = 1;", "let x = 2;\n");
+        assert_eq!(
+            apply_edit("fn f() {\n    let x = 1;\n}\n", &indented).unwrap(),
+            "fn f() {\n    let x = 2;\n}\n"
+        );
+    }
+
+    #[test]
+    fn replace_lines_swaps_a_range_and_checks_bounds() {
+        let src = "a\nb\nb\nc\n";
+        let json = r#"[{"id":"1","type":"function","function":{"name":"replace_lines","arguments":"{\"file_path\":\"f.txt\",\"start_line\":2,\"end_line\":3,\"new_string\":\"B\"}"}}]"#;
+        let e = parse_edits(json).unwrap().edits.remove(0);
+        assert_eq!(e.op, "replace_lines");
+        assert_eq!(apply_edit(src, &e).unwrap(), "a\nB\nc\n");
+
+        let mut delete = e.clone();
+        delete.snippet.clear();
+        assert_eq!(apply_edit(src, &delete).unwrap(), "a\nc\n");
+
+        let mut past_end = e;
+        past_end.end_line = Some(5);
+        let err = apply_edit(src, &past_end).unwrap_err().to_string();
+        assert!(
+            err.contains("2-5 is out of bounds; the file has 4 lines"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn effective_edit_count_skips_no_ops_and_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.rs"), "let x = 1;\n").unwrap();
+        let mut no_op = edit("replace", "let x = 1;", "let x = 1;");
+        no_op.path = "a.rs".into();
+        let mut change = edit("replace", "x = 1", "x = 2");
+        change.path = "a.rs".into();
+        let mut stacked = edit("replace", "x = 2", "x = 3");
+        stacked.path = "a.rs".into();
+        let mut missing = edit("replace", "nowhere", "y");
+        missing.path = "a.rs".into();
+        let mut created = edit("create_file", "", "new\n");
+        created.path = "b.rs".into();
+        let batch = EditBatch {
+            edits: vec![no_op, change, stacked, missing, created],
+        };
+
+        assert_eq!(effective_edit_count(&batch, dir.path()), 3);
+        assert_eq!(prepare_batch(&batch, dir.path(), true).len(), 2);
+    }
+
+    #[test]
+    fn dedupe_batch_drops_duplicates_and_overlapping_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+        let at = |mut e: Edit| {
+            e.path = "a.rs".into();
+            e
+        };
+        let mut batch = EditBatch {
+            edits: vec![
+                at(edit("replace", "fn a() {}", "fn a() { 1 }")),
+                at(edit("replace", "fn a() {}", "fn a() { 1 }")),
+                at(edit("insert_after", "fn b() {}", "\nfn c() {}")),
+                at(edit("replace", "a() {}", "a() { 2 }")),
+                at(edit("insert_before", "fn a() {}", "// a\n")),
+                at(edit("insert_after", "fn b() {}", "\nfn d() {}")),
+            ],
+        };
+
+        let warnings = dedupe_batch(&mut batch, dir.path());
+        let snippets: Vec<_> = batch.edits.iter().map(|e| e.snippet.as_str()).collect();
+        assert_eq!(snippets, ["fn a() { 1 }", "\nfn c() {}", "\nfn d() {}"]);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("a.rs at line 1"), "{}", warnings[0]);
+    }
+
+    #[test]
+    fn batch_summary_groups_ops_by_file_in_order() {
+        let at = |path: &str, mut e: Edit| {
+            e.path = path.into();
+            e
+        };
+        let batch = EditBatch {
+            edits: vec![
+                at("src/b.rs", edit("replace", "x", "y")),
+                at("src/a.rs", edit("create_file", "", "fn a() {}")),
+                at("src/b.rs", edit("insert_after", "y", "z")),
+            ],
+        };
+        assert_eq!(
+            batch_summary(&batch),
+            ["src/b.rs: replace, insert_after", "src/a.rs: create_file"]
+        );
+    }
+
+    #[test]
+    fn replace_regex_tool_call_applies_with_a_default_limit_of_one() {
+        let args = serde_json::json!({
+            "file_path": "a.rs",
+            "pattern": r"old_(\w+)",
+            "replacement": "new_$1"
+        });
+        let mut e = edit_from_tool_call("replace_regex", &args).unwrap();
+        assert_eq!((e.op.as_str(), e.limit), ("replace_regex", 1));
+        let src = "old_a(); old_b();\n";
+        assert_eq!(apply_edit(src, &e).unwrap(), "new_a(); old_b();\n");
+
+        e = edit_from_tool_call(
+            "replace_regex",
+            &serde_json::json!({"file_path": "a.rs", "pattern": r"old_(\w+)", "replacement": "new_$1", "limit": 5}),
+        )
+        .unwrap();
+        assert_eq!(apply_edit(src, &e).unwrap(), "new_a(); new_b();\n");
+    }
+
+    #[test]
+    fn parse_actions_maps_insert_tools_and_inline_arguments() {
+        let json = r#"[{"type":"function","function":{"name":"insert_after","arguments":{"file_path":"a.rs","anchor":"use std::fs;","new_string":"\nuse std::io;"}}}]"#;
+        let e = parse_edits(json).unwrap().edits.remove(0);
+        assert_eq!(e.op, "insert_after");
+        assert_eq!(e.anchor, "use std::fs;");
+        assert_eq!(
+            apply_edit("use std::fs;\n", &e).unwrap(),
+            "use std::fs;\nuse std::io;\n"
+        );
+
+        let args = serde_json::json!({"file_path": "a.rs", "anchor": "x"});
+        assert!(edit_from_tool_call("insert_before", &args).is_none());
+        assert!(edit_from_tool_call("replace_regex", &args).is_none());
+        assert!(edit_from_tool_call("search", &args).is_none());
+    }
+
+    #[test]
+    fn parse_edits_unwraps_edits_and_tool_calls_objects() {
+        let call = r#"{"type":"function","function":{"name":"edit","arguments":{"file_path":"a.rs","old_string":"a","new_string":"b"}}}"#;
+        for wrapped in [
+            format!(r#"{{"edits":[{call}]}}"#),
+            format!(r#"{{"tool_calls":[{call}]}}"#),
+            format!("Here you go:\n```json\n{{\"note\":\"x\",\"edits\":[{call}]}}\n```"),
+            format!("[{call}]"),
+        ] {
+            let batch = parse_edits(&wrapped).unwrap();
+            assert_eq!(batch.edits.len(), 1, "{wrapped}");
+            assert_eq!(batch.edits[0].snippet, "b");
+        }
+        assert!(parse_edits(r#"{"edits":"none"}"#).is_err());
+    }
+
+    #[test]
+    fn parse_actions_reads_create_file_tool_calls() {
+        let json = r#"[{"id":"1","type":"function","function":{"name":"create_file","arguments":"{\"file_path\":\"a.txt\",\"content\":\"hi\"}"}}]"#;
+        let batch = parse_edits(json).unwrap();
+        assert_eq!(batch.edits.len(), 1);
+        assert_eq!(batch.edits[0].op, "create_file");
+        assert_eq!(batch.edits[0].snippet, "hi");
+        assert!(!batch.edits[0].overwrite);
     }
 }
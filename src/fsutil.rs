@@ -1,9 +1,16 @@
 use anyhow::{Context, Result};
 use std::{
     fs,
+    io::{self, Read, Write},
     path::{Path, PathBuf},
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
 };
-use tempfile::NamedTempFile;
+use tempfile::Builder;
+
+/// Prefix of the temp files writes are staged in, so the file watcher can
+/// tell them apart from the user's own files.
+pub const TEMP_PREFIX: &str = ".smol-tmp";
 
 pub fn ensure_inside_repo(repo_root: &Path, path: &Path) -> Result<PathBuf> {
     let root = std::fs::canonicalize(repo_root).context("canonicalize root")?;
@@ -23,27 +30,504 @@ pub fn backup_path(backup_root: &Path, abs: &Path, repo_root: &Path) -> Result<P
 }
 
 pub fn backup_and_write(abs: &Path, new_contents: &str, backup_file: &Path) -> Result<()> {
+    write_backed_up(&backups_dir()?, abs, new_contents, backup_file)
+}
+
+fn write_backed_up(
+    backups: &Path,
+    abs: &Path,
+    new_contents: &str,
+    backup_file: &Path,
+) -> Result<()> {
     if let Some(parent) = backup_file.parent() {
         fs::create_dir_all(parent).ok();
     }
-    if abs.exists() {
-        if let Err(e) = fs::copy(abs, backup_file) {
-            eprintln!("warning: failed to backup {}: {e}", abs.display());
-        }
+    if !abs.exists() {
+        record_created(backups, backup_file).ok();
+    } else if let Err(e) = fs::copy(abs, backup_file) {
+        eprintln!("warning: failed to backup {}: {e}", abs.display());
     }
     if let Some(parent) = abs.parent() {
         fs::create_dir_all(parent).ok();
     }
-    let mut tmp = NamedTempFile::new_in(abs.parent().unwrap_or(Path::new(".")))
+    let mut tmp = Builder::new()
+        .prefix(TEMP_PREFIX)
+        .tempfile_in(abs.parent().unwrap_or(Path::new(".")))
         .context("create temp file")?;
     std::io::Write::write_all(&mut tmp, new_contents.as_bytes()).context("write temp")?;
     tmp.persist(abs).context("atomic swap")?;
     Ok(())
 }
 
+static REPO_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Resolves `--repo` (or the current directory without it) to a canonical
+/// directory and makes it the root that `.smol` lives under.
+pub fn init_repo_root(repo: Option<&Path>) -> Result<PathBuf> {
+    let root = resolve_repo_root(repo, &std::env::current_dir()?)?;
+    Ok(REPO_ROOT.get_or_init(|| root).clone())
+}
+
+/// `repo`, relative to `cwd`, or `cwd` itself, as a canonical directory.
+fn resolve_repo_root(repo: Option<&Path>, cwd: &Path) -> Result<PathBuf> {
+    let path = repo.map_or_else(|| cwd.to_path_buf(), |repo| cwd.join(repo));
+    if !path.is_dir() {
+        anyhow::bail!("repo root {} is not a directory", path.display());
+    }
+    fs::canonicalize(&path).with_context(|| format!("canonicalize {}", path.display()))
+}
+
+/// `.smol` under the repo root (the current directory unless `--repo` set
+/// one). Not created until something is written under it.
 pub fn smol_dir() -> Result<PathBuf> {
-    let here = std::env::current_dir()?;
-    let p = here.join(".smol");
-    std::fs::create_dir_all(&p).ok();
-    Ok(p)
+    let root = match REPO_ROOT.get() {
+        Some(root) => root.clone(),
+        None => std::env::current_dir()?,
+    };
+    Ok(root.join(".smol"))
+}
+
+fn backups_dir() -> Result<PathBuf> {
+    Ok(smol_dir()?.join("backups"))
+}
+
+/// `.smol/backups/<unix-secs>` for a batch of backups. Not created until the
+/// first backup is written under it, so a batch that writes nothing leaves no
+/// trace.
+pub fn timestamp_dir() -> Result<PathBuf> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    Ok(backups_dir()?.join(now.to_string()))
+}
+
+/// Bytes sampled from the start of a file to decide whether it is binary.
+const BINARY_SNIFF_BYTES: u64 = 8 * 1024;
+
+/// A file read for the model's context.
+#[derive(Debug, PartialEq, Eq)]
+pub enum FileText {
+    Text(String),
+    /// A binary file of this many bytes; only its first few KB were read.
+    Binary(u64),
+}
+
+impl FileText {
+    /// The text, or a short note standing in for a binary file.
+    pub fn into_text(self) -> String {
+        match self {
+            FileText::Text(text) => text,
+            FileText::Binary(bytes) => binary_note(bytes),
+        }
+    }
+}
+
+pub fn binary_note(bytes: u64) -> String {
+    format!("binary file, {bytes} bytes, skipped")
+}
+
+/// Reads `path` as UTF-8 unless its first few KB contain a NUL byte or
+/// invalid UTF-8, in which case only its size is reported.
+pub fn read_text(path: &Path) -> io::Result<FileText> {
+    let mut file = fs::File::open(path)?;
+    let mut buf = Vec::new();
+    (&mut file).take(BINARY_SNIFF_BYTES).read_to_end(&mut buf)?;
+    if looks_binary(&buf) {
+        return Ok(FileText::Binary(file.metadata()?.len()));
+    }
+    file.read_to_end(&mut buf)?;
+    String::from_utf8(buf)
+        .map(FileText::Text)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// A sample counts as text when it has no NUL bytes and is valid UTF-8, save
+/// for a character cut off at the end of the sample.
+pub fn looks_binary(sample: &[u8]) -> bool {
+    if sample.contains(&0) {
+        return true;
+    }
+    match std::str::from_utf8(sample) {
+        Ok(_) => false,
+        Err(err) => err.error_len().is_some(),
+    }
+}
+
+/// Largest image sent to a multimodal model.
+const MAX_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// The MIME type of an image the model can be sent, judged by extension.
+pub fn image_mime(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// Reads an image into a `data:` URL for a multimodal message.
+pub fn image_data_url(path: &Path) -> Result<String> {
+    let mime = image_mime(path)
+        .with_context(|| format!("{} is not a PNG, JPEG, GIF or WebP image", path.display()))?;
+    let len = fs::metadata(path)
+        .with_context(|| format!("read {}", path.display()))?
+        .len();
+    if len > MAX_IMAGE_BYTES {
+        anyhow::bail!(
+            "{} is {len} bytes; images are limited to {MAX_IMAGE_BYTES}",
+            path.display()
+        );
+    }
+    let bytes = fs::read(path).with_context(|| format!("read {}", path.display()))?;
+    Ok(format!(
+        "data:{mime};base64,{}",
+        crate::util::base64_encode(&bytes)
+    ))
+}
+
+/// Cheap guard against edits to absolute or hidden/system paths.
+pub fn is_write_blocked(path: &str) -> bool {
+    path.starts_with('/') || path.starts_with('.')
+}
+
+/// Maps a backup at `.smol/backups/<ts>/REL/PATH` back to `repo_root/REL/PATH`.
+pub fn target_from_backup(repo_root: &Path, backup: &Path) -> Option<PathBuf> {
+    backup_target(&backups_dir().ok()?, repo_root, backup)
+}
+
+fn backup_target(backups: &Path, repo_root: &Path, backup: &Path) -> Option<PathBuf> {
+    let rel = backup.strip_prefix(backups).ok()?;
+    let comps: Vec<_> = rel.components().collect();
+    if comps.len() < 2 {
+        return None;
+    }
+    let stripped: PathBuf = comps.iter().skip(1).collect();
+    Some(repo_root.join(stripped))
+}
+
+/// What [`restore_backup`] did to the original file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Restored {
+    /// The backed-up contents were copied back.
+    Reverted,
+    /// There was no backup because the batch created the file, so it was
+    /// deleted.
+    Removed,
+    /// The file was already gone.
+    Missing,
+}
+
+/// Puts the file `backup` was taken from back the way it was before the edit.
+/// A target that resolves outside the repo is refused.
+pub fn restore_backup(repo_root: &Path, backup: &Path) -> Result<(PathBuf, Restored)> {
+    restore_from(&backups_dir()?, repo_root, backup)
+}
+
+fn restore_from(backups: &Path, repo_root: &Path, backup: &Path) -> Result<(PathBuf, Restored)> {
+    let target = backup_target(backups, repo_root, backup)
+        .with_context(|| format!("could not determine target for {}", backup.display()))?;
+    let target = ensure_inside_repo(repo_root, &target)
+        .with_context(|| format!("refusing to restore {}", target.display()))?;
+    if backup.exists() {
+        fs::copy(backup, &target).with_context(|| format!("restore {}", target.display()))?;
+        return Ok((target, Restored::Reverted));
+    }
+    match fs::remove_file(&target) {
+        Ok(()) => Ok((target, Restored::Removed)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok((target, Restored::Missing)),
+        Err(err) => Err(err).with_context(|| format!("remove {}", target.display())),
+    }
+}
+
+/// Lists, in each timestamp directory, the files written there that did not
+/// exist before; they have no backup to restore. Hidden, so it cannot clash
+/// with a backed-up repo file.
+const CREATED_MANIFEST: &str = ".created";
+
+/// Notes in the timestamp directory's manifest that `backup_file` stands for
+/// a newly created file.
+fn record_created(backups: &Path, backup_file: &Path) -> Result<()> {
+    let mut comps = backup_file.strip_prefix(backups)?.components();
+    let timestamp = comps.next().context("backup outside a timestamp dir")?;
+    let mut manifest = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(backups.join(timestamp).join(CREATED_MANIFEST))?;
+    writeln!(manifest, "{}", comps.as_path().display())?;
+    Ok(())
+}
+
+/// Every backup under `.smol/backups/<timestamp>`, plus the would-be backup
+/// paths of files created there, which [`restore_backup`] deletes.
+pub fn backup_set(timestamp: &str) -> Result<Vec<PathBuf>> {
+    if timestamp.is_empty() || !timestamp.bytes().all(|b| b.is_ascii_digit()) {
+        anyhow::bail!("{timestamp:?} is not a backup timestamp (see /backups)");
+    }
+    let dir = backups_dir()?.join(timestamp);
+    if !dir.is_dir() {
+        anyhow::bail!("no backups from {timestamp} (see /backups)");
+    }
+    read_backup_set(&dir)
+}
+
+fn read_backup_set(dir: &Path) -> Result<Vec<PathBuf>> {
+    let manifest = dir.join(CREATED_MANIFEST);
+    let mut created: Vec<PathBuf> = match fs::read_to_string(&manifest) {
+        Ok(text) => text
+            .lines()
+            .filter(|line| !line.is_empty())
+            .filter(|line| {
+                // The manifest may come with the repo: only plain relative
+                // paths are taken, so a rollback cannot reach outside it.
+                let plain = Path::new(line)
+                    .components()
+                    .all(|c| matches!(c, std::path::Component::Normal(_)));
+                if !plain {
+                    tracing::warn!("{}: ignoring entry {line:?}", manifest.display());
+                }
+                plain
+            })
+            .map(|line| dir.join(line))
+            .collect(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err).with_context(|| format!("read {}", manifest.display())),
+    };
+    created.sort();
+    created.dedup();
+    let mut set: Vec<PathBuf> = walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file() && entry.path() != manifest)
+        .map(walkdir::DirEntry::into_path)
+        .filter(|path| !created.contains(path))
+        .collect();
+    set.sort();
+    set.extend(created);
+    Ok(set)
+}
+
+/// Timestamps under `.smol/backups` with how many files each touched, newest
+/// first. Directories of batches that wrote nothing are left out.
+pub fn list_backup_sets() -> Result<Vec<(String, usize)>> {
+    let entries = match fs::read_dir(backups_dir()?) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).context("read .smol/backups"),
+    };
+    let mut timestamps: Vec<(u64, String)> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            Some((name.parse().ok()?, name))
+        })
+        .collect();
+    timestamps.sort_unstable_by(|a, b| b.cmp(a));
+    Ok(timestamps
+        .into_iter()
+        .filter_map(|(_, name)| {
+            let files = backup_set(&name).map(|set| set.len()).unwrap_or(0);
+            (files > 0).then_some((name, files))
+        })
+        .collect())
+}
+
+/// Backups of applied edits, grouped by the batch that wrote them, for `/undo`
+/// (one file) and `/revert` (a whole batch).
+#[derive(Debug, Default)]
+pub struct BackupStack {
+    batches: Vec<Vec<PathBuf>>,
+}
+
+impl BackupStack {
+    /// Starts a new batch; later pushes go into it.
+    pub fn begin_batch(&mut self) {
+        self.batches.push(Vec::new());
+    }
+
+    pub fn push(&mut self, backup: PathBuf) {
+        match self.batches.last_mut() {
+            Some(batch) => batch.push(backup),
+            None => self.batches.push(vec![backup]),
+        }
+    }
+
+    /// The most recent backup across all batches.
+    pub fn pop_file(&mut self) -> Option<PathBuf> {
+        self.batches.iter_mut().rev().find_map(Vec::pop)
+    }
+
+    /// Every backup from the most recent batch that still has any, newest
+    /// first.
+    pub fn pop_batch(&mut self) -> Option<Vec<PathBuf>> {
+        while let Some(mut batch) = self.batches.pop() {
+            if !batch.is_empty() {
+                batch.reverse();
+                return Some(batch);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repo_root_resolves_against_cwd_and_rejects_non_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let cwd = dir.path().canonicalize().unwrap();
+        fs::create_dir(cwd.join("project")).unwrap();
+        fs::write(cwd.join("file.txt"), "").unwrap();
+
+        assert_eq!(resolve_repo_root(None, &cwd).unwrap(), cwd);
+        assert_eq!(
+            resolve_repo_root(Some(Path::new("project/../project")), &cwd).unwrap(),
+            cwd.join("project")
+        );
+        assert!(resolve_repo_root(Some(Path::new("missing")), &cwd).is_err());
+        assert!(resolve_repo_root(Some(Path::new("file.txt")), &cwd).is_err());
+    }
+
+    #[test]
+    fn backup_stack_pops_files_and_whole_batches() {
+        let mut stack = BackupStack::default();
+        stack.begin_batch();
+        stack.push("a".into());
+        stack.begin_batch();
+        stack.push("b".into());
+        stack.push("c".into());
+        stack.begin_batch();
+
+        assert_eq!(stack.pop_file(), Some("c".into()));
+        assert_eq!(stack.pop_batch(), Some(vec!["b".into()]));
+        assert_eq!(stack.pop_batch(), Some(vec!["a".into()]));
+        assert_eq!(stack.pop_file(), None);
+    }
+
+    #[test]
+    fn backup_set_lists_created_files_after_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let set_dir = dir.path();
+        fs::create_dir_all(set_dir.join("src")).unwrap();
+        fs::write(set_dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(set_dir.join("README.md"), "# x\n").unwrap();
+        fs::write(
+            set_dir.join(CREATED_MANIFEST),
+            "src/new.rs\nsrc/new.rs\nnotes.md\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            read_backup_set(set_dir).unwrap(),
+            [
+                set_dir.join("README.md"),
+                set_dir.join("src/main.rs"),
+                set_dir.join("notes.md"),
+                set_dir.join("src/new.rs"),
+            ]
+        );
+        assert!(backup_set("../outside").is_err());
+    }
+
+    #[test]
+    fn rollback_removes_created_files_and_stays_inside_the_repo() {
+        let outer = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(outer.path()).unwrap().join("repo");
+        let backups = root.join(".smol/backups");
+        let set_dir = backups.join("100");
+        fs::create_dir_all(set_dir.join("src")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/kept.rs"), "new\n").unwrap();
+        fs::write(set_dir.join("src/kept.rs"), "old\n").unwrap();
+        fs::write(root.join("src/created.rs"), "fn f() {}\n").unwrap();
+        record_created(&backups, &set_dir.join("src/created.rs")).unwrap();
+        let victim = outer.path().join("victim.txt");
+        fs::write(&victim, "keep me\n").unwrap();
+        let mut manifest = fs::OpenOptions::new()
+            .append(true)
+            .open(set_dir.join(CREATED_MANIFEST))
+            .unwrap();
+        writeln!(manifest, "../../../../victim.txt").unwrap();
+        writeln!(manifest, "{}", victim.display()).unwrap();
+
+        let set = read_backup_set(&set_dir).unwrap();
+        assert_eq!(
+            set,
+            [set_dir.join("src/kept.rs"), set_dir.join("src/created.rs")]
+        );
+        for backup in &set {
+            restore_from(&backups, &root, backup).unwrap();
+        }
+        assert_eq!(
+            fs::read_to_string(root.join("src/kept.rs")).unwrap(),
+            "old\n"
+        );
+        assert!(!root.join("src/created.rs").exists());
+
+        let escape = set_dir.join("../victim.txt");
+        assert!(restore_from(&backups, &root, &escape).is_err());
+        assert!(victim.exists());
+    }
+
+    #[test]
+    fn created_file_round_trips_through_undo_and_redo() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        let backups = root.join(".smol/backups");
+        let target = root.join("src/new.rs");
+        let write = |ts: &str, contents: &str| {
+            let backup = backup_path(&backups.join(ts), &target, &root).unwrap();
+            write_backed_up(&backups, &target, contents, &backup).unwrap();
+            backup
+        };
+
+        // Apply creates the file and, lazily, its timestamp dir; undo removes it.
+        assert!(!backups.exists());
+        let applied = write("100", "fn new() {}\n");
+        let set = read_backup_set(&backups.join("100")).unwrap();
+        assert_eq!(set, std::slice::from_ref(&applied));
+        let current = fs::read_to_string(&target).unwrap();
+        let (_, undone) = restore_from(&backups, &root, &applied).unwrap();
+        assert_eq!(undone, Restored::Removed);
+        assert!(!target.exists());
+
+        // Redo recreates it with the same contents, and can itself be undone.
+        let redone = write("101", &current);
+        assert_eq!(fs::read_to_string(&target).unwrap(), "fn new() {}\n");
+        let (_, undone) = restore_from(&backups, &root, &redone).unwrap();
+        assert_eq!(undone, Restored::Removed);
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn read_text_reports_binary_files_by_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("a.rs");
+        fs::write(&text, "fn main() {}\n").unwrap();
+        let png = dir.path().join("logo.png");
+        fs::write(&png, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+        let latin1 = dir.path().join("notes.txt");
+        fs::write(&latin1, b"caf\xe9 au lait").unwrap();
+
+        assert_eq!(
+            read_text(&text).unwrap(),
+            FileText::Text("fn main() {}\n".into())
+        );
+        assert_eq!(read_text(&png).unwrap(), FileText::Binary(16));
+        assert_eq!(
+            read_text(&latin1).unwrap().into_text(),
+            "binary file, 12 bytes, skipped"
+        );
+        assert!(!looks_binary("é".as_bytes().split_last().unwrap().1));
+    }
+
+    #[test]
+    fn write_guard_blocks_absolute_and_hidden_paths() {
+        assert!(is_write_blocked("/etc/passwd"));
+        assert!(is_write_blocked(".git/config"));
+        assert!(is_write_blocked("../outside.rs"));
+        assert!(!is_write_blocked("src/main.rs"));
+    }
 }
@@ -0,0 +1,85 @@
+use anyhow::{Context, Result};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Longest commit subject `autocommit` writes, including the `smol: ` prefix.
+const MAX_SUBJECT_CHARS: usize = 72;
+
+/// Stages `paths` and commits only them, leaving anything else the user has
+/// staged untouched. Returns the short hash of the new commit.
+pub fn autocommit(repo_root: &Path, paths: &[PathBuf], prompt: &str) -> Result<String> {
+    git(repo_root, &["rev-parse", "--is-inside-work-tree"]).context("not a git repository")?;
+
+    let mut add = vec!["add".into(), "-A".into(), "--".into()];
+    add.extend(paths.iter().map(|p| p.display().to_string()));
+    git(repo_root, &add)?;
+
+    let message = commit_message(prompt);
+    let mut commit = vec!["commit".into(), "-m".into(), message, "--".into()];
+    commit.extend(paths.iter().map(|p| p.display().to_string()));
+    git(repo_root, &commit)?;
+
+    git(repo_root, &["rev-parse", "--short", "HEAD"])
+}
+
+/// Whether `git status` reports `path` as modified, staged or untracked.
+/// Anything outside a git work tree counts as clean.
+pub fn is_dirty(repo_root: &Path, path: &Path) -> bool {
+    let path = path.display().to_string();
+    git(repo_root, &["status", "--porcelain", "--", &path])
+        .map(|out| !out.is_empty())
+        .unwrap_or(false)
+}
+
+/// `smol: <first line of the prompt>`, truncated to fit a commit subject.
+pub fn commit_message(prompt: &str) -> String {
+    let first_line = prompt.lines().next().unwrap_or_default().trim();
+    let prefix = "smol: ";
+    let budget = MAX_SUBJECT_CHARS - prefix.len();
+    if first_line.chars().count() <= budget {
+        return format!("{prefix}{first_line}");
+    }
+    let truncated: String = first_line.chars().take(budget - 3).collect();
+    format!("{prefix}{}...", truncated.trim_end())
+}
+
+fn git<S: AsRef<str>>(repo_root: &Path, args: &[S]) -> Result<String> {
+    let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+    let output = Command::new("git")
+        .args(&args)
+        .current_dir(repo_root)
+        .output()
+        .context("failed to run git")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git {} failed: {}", args.join(" "), stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commit_message_uses_first_line_and_truncates() {
+        assert_eq!(commit_message("Add logging\nmore"), "smol: Add logging");
+        let long = "x".repeat(100);
+        let msg = commit_message(&long);
+        assert_eq!(msg.chars().count(), MAX_SUBJECT_CHARS);
+        assert!(msg.ends_with("..."));
+    }
+
+    #[test]
+    fn is_dirty_reports_untracked_files_only_inside_a_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "hi").unwrap();
+        assert!(!is_dirty(dir.path(), Path::new("a.txt")));
+
+        git(dir.path(), &["init", "-q"]).unwrap();
+        assert!(is_dirty(dir.path(), Path::new("a.txt")));
+        assert!(!is_dirty(dir.path(), Path::new("missing.txt")));
+    }
+}
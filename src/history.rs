@@ -0,0 +1,184 @@
+//! Prompt and command history shared across sessions (`.smol/history`), for
+//! recall with Up/Down in chat mode and Ctrl+P/Ctrl+N in the TUI.
+
+use anyhow::{Context, Result};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+
+use crate::fsutil;
+
+/// Entries kept on disk; older ones are dropped when the file is rewritten.
+const MAX_HISTORY_ENTRIES: usize = 2_000;
+
+#[derive(Debug, Default)]
+pub struct PromptHistory {
+    entries: Vec<String>,
+    /// `None` keeps history in memory only (dry runs).
+    path: Option<PathBuf>,
+}
+
+impl PromptHistory {
+    /// Loads `.smol/history`. With `persist` off nothing is read or written.
+    pub fn load(persist: bool) -> Self {
+        let path = persist
+            .then(|| fsutil::smol_dir().ok().map(|dir| dir.join("history")))
+            .flatten();
+        let entries = path
+            .as_ref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .map(|text| text.lines().map(decode).collect())
+            .unwrap_or_default();
+        Self { entries, path }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Records `entry` unless it repeats the previous one, appending it to the
+    /// history file. Write failures are logged and otherwise ignored.
+    pub fn push(&mut self, entry: &str) {
+        if entry.trim().is_empty() || self.entries.last().is_some_and(|last| last == entry) {
+            return;
+        }
+        self.entries.push(entry.to_string());
+        if let Err(err) = self.save(entry) {
+            tracing::debug!("failed to save history: {err:#}");
+        }
+    }
+
+    fn save(&mut self, entry: &str) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("create .smol")?;
+        }
+        if self.entries.len() > MAX_HISTORY_ENTRIES {
+            let excess = self.entries.len() - MAX_HISTORY_ENTRIES;
+            self.entries.drain(..excess);
+            let text: String = self
+                .entries
+                .iter()
+                .map(|e| format!("{}\n", encode(e)))
+                .collect();
+            return fs::write(path, text).context("rewrite history");
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context("open history")?;
+        writeln!(file, "{}", encode(entry)).context("append history")
+    }
+}
+
+/// One entry per line: multi-line TUI prompts have their newlines escaped.
+fn encode(entry: &str) -> String {
+    entry.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn decode(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Walks backwards and forwards through history from an editable draft, like
+/// a shell prompt.
+#[derive(Debug, Default)]
+pub struct Recall {
+    index: Option<usize>,
+    draft: String,
+}
+
+impl Recall {
+    /// The entry before the current one; `current` is kept as the draft when
+    /// recall starts. `None` at the oldest entry or with no history.
+    pub fn older(&mut self, entries: &[String], current: &str) -> Option<String> {
+        let next = match self.index {
+            None => {
+                self.draft = current.to_string();
+                entries.len().checked_sub(1)?
+            }
+            Some(0) => return None,
+            Some(i) => i - 1,
+        };
+        self.index = Some(next);
+        entries.get(next).cloned()
+    }
+
+    /// The entry after the current one, or the draft once past the newest.
+    pub fn newer(&mut self, entries: &[String]) -> Option<String> {
+        let i = self.index?;
+        if i + 1 < entries.len() {
+            self.index = Some(i + 1);
+            return entries.get(i + 1).cloned();
+        }
+        self.index = None;
+        Some(std::mem::take(&mut self.draft))
+    }
+
+    /// Forgets the position, e.g. once a prompt is submitted.
+    pub fn reset(&mut self) {
+        self.index = None;
+        self.draft.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_dedupes_consecutive_entries_and_round_trips_newlines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+        let mut history = PromptHistory {
+            entries: Vec::new(),
+            path: Some(path.clone()),
+        };
+        history.push("fix the bug");
+        history.push("fix the bug");
+        history.push("line one\nline \\two");
+        history.push("fix the bug");
+
+        let saved: Vec<String> = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(decode)
+            .collect();
+        assert_eq!(
+            saved,
+            vec!["fix the bug", "line one\nline \\two", "fix the bug"]
+        );
+        assert_eq!(history.entries(), saved.as_slice());
+    }
+
+    #[test]
+    fn recall_walks_history_and_restores_the_draft() {
+        let entries = vec!["a".to_string(), "b".to_string()];
+        let mut recall = Recall::default();
+        assert_eq!(recall.newer(&entries), None);
+        assert_eq!(recall.older(&entries, "draft").as_deref(), Some("b"));
+        assert_eq!(recall.older(&entries, "b").as_deref(), Some("a"));
+        assert_eq!(recall.older(&entries, "a"), None);
+        assert_eq!(recall.newer(&entries).as_deref(), Some("b"));
+        assert_eq!(recall.newer(&entries).as_deref(), Some("draft"));
+        assert_eq!(recall.newer(&entries), None);
+    }
+}
@@ -0,0 +1,123 @@
+//! On-disk cache of the provider's model catalog (`.smol/models.json`), so
+//! `/model` and cost estimates work without refetching the list every time.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{Model, http_client, list_models};
+use crate::{config::AppConfig, fsutil};
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    fetched_at: u64,
+    /// The catalog belongs to this endpoint; switching providers misses.
+    base_url: String,
+    models: Vec<Model>,
+}
+
+fn cache_path() -> Result<PathBuf> {
+    Ok(fsutil::smol_dir()?.join("models.json"))
+}
+
+/// The cached catalog for the configured provider, if it is younger than
+/// `runtime.model_cache_ttl_hours`.
+pub fn cached_models(cfg: &AppConfig) -> Option<Vec<Model>> {
+    read_cache(&cache_path().ok()?, cfg, unix_now())
+}
+
+/// The catalog from the cache when fresh, otherwise fetched and cached
+/// (except in dry-run mode, which writes nothing). `refresh` always fetches.
+/// The flag is true when the cache was used.
+pub async fn list_models_cached(cfg: &AppConfig, refresh: bool) -> Result<(Vec<Model>, bool)> {
+    if !refresh && let Some(models) = cached_models(cfg) {
+        return Ok((models, true));
+    }
+    let models = list_models(cfg, &http_client(cfg)?).await?;
+    if let Err(err) = write_cache(&cache_path()?, cfg, &models, unix_now()) {
+        tracing::debug!("failed to cache models: {err:#}");
+    }
+    Ok((models, false))
+}
+
+/// Catalog info for `id`, from the cache when fresh or fetched otherwise.
+/// `None` when the provider does not list that model.
+pub async fn find_model(cfg: &AppConfig, id: &str) -> Result<Option<Model>> {
+    let (models, _) = list_models_cached(cfg, false).await?;
+    Ok(models.into_iter().find(|m| m.id == id))
+}
+
+fn read_cache(path: &Path, cfg: &AppConfig, now: u64) -> Option<Vec<Model>> {
+    let ttl = cfg.runtime.model_cache_ttl_hours.saturating_mul(3600);
+    let cache: CacheFile = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    let fresh = now.saturating_sub(cache.fetched_at) < ttl;
+    (fresh && cache.base_url == cfg.provider.base_url).then_some(cache.models)
+}
+
+fn write_cache(path: &Path, cfg: &AppConfig, models: &[Model], now: u64) -> Result<()> {
+    if cfg.runtime.dry_run {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("create .smol")?;
+    }
+    let cache = CacheFile {
+        fetched_at: now,
+        base_url: cfg.provider.base_url.clone(),
+        models: models.to_vec(),
+    };
+    fs::write(path, serde_json::to_string(&cache)?).context("write models cache")
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_expires_after_ttl_and_is_scoped_to_base_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("models.json");
+        let mut cfg = AppConfig::default();
+        let models = vec![Model {
+            id: "openai/gpt-4o".into(),
+            name: "GPT-4o".into(),
+            prompt_cost: Some(0.000_002_5),
+            completion_cost: None,
+            context_length: Some(128_000),
+        }];
+        write_cache(&path, &cfg, &models, 1_000).unwrap();
+
+        let hit = read_cache(&path, &cfg, 1_000 + 3_600).unwrap();
+        assert_eq!(hit[0].id, "openai/gpt-4o");
+        assert_eq!(hit[0].context_length, Some(128_000));
+        assert!(read_cache(&path, &cfg, 1_000 + 24 * 3_600).is_none());
+
+        cfg.runtime.model_cache_ttl_hours = 0;
+        assert!(read_cache(&path, &cfg, 1_000).is_none());
+
+        cfg.runtime.model_cache_ttl_hours = 24;
+        cfg.provider.base_url = "http://localhost:11434".into();
+        assert!(read_cache(&path, &cfg, 1_000).is_none());
+    }
+
+    #[test]
+    fn dry_run_does_not_write_the_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".smol/models.json");
+        let mut cfg = AppConfig::default();
+        cfg.runtime.dry_run = true;
+        write_cache(&path, &cfg, &[], 1_000).unwrap();
+        assert!(!dir.path().join(".smol").exists());
+    }
+}
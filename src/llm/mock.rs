@@ -0,0 +1,113 @@
+//! A stand-in `chat/completions` server for tests, so the request, parse and
+//! apply flow can run end to end without a real provider.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+
+use serde_json::Value;
+
+use crate::config::{AppConfig, ProviderKind};
+
+pub(crate) struct MockServer {
+    base_url: String,
+    requests: Arc<Mutex<Vec<Value>>>,
+}
+
+impl MockServer {
+    /// Answers one request per entry of `replies`, in order, then stops.
+    pub(crate) fn start(replies: Vec<Value>) -> MockServer {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock server");
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&requests);
+        std::thread::spawn(move || {
+            for reply in replies {
+                let Ok((mut stream, _)) = listener.accept() else {
+                    return;
+                };
+                let body = read_request_body(&mut BufReader::new(&stream));
+                seen.lock()
+                    .unwrap()
+                    .push(serde_json::from_slice(&body).unwrap_or(Value::Null));
+                let (status, extra) = match reply.get(MOCK_STATUS) {
+                    Some(status) => (status.as_u64().unwrap_or(500), "Retry-After: 0\r\n"),
+                    None => (200, ""),
+                };
+                let reply = reply.to_string();
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {status} Mock\r\nContent-Type: application/json\r\n{extra}Content-Length: {}\r\nConnection: close\r\n\r\n{reply}",
+                    reply.len()
+                );
+            }
+        });
+        MockServer { base_url, requests }
+    }
+
+    /// A config whose provider is this server.
+    pub(crate) fn config(&self) -> AppConfig {
+        let mut cfg = AppConfig::default();
+        cfg.provider.kind = ProviderKind::OpenAi;
+        cfg.provider.base_url = self.base_url.clone();
+        cfg.provider.model = "mock-model".into();
+        cfg.auth.api_key = "test-key".into();
+        cfg.runtime.request_timeout_secs = 5;
+        cfg
+    }
+
+    /// JSON bodies of the requests answered so far.
+    pub(crate) fn requests(&self) -> Vec<Value> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Marks a reply as an error response with this HTTP status.
+const MOCK_STATUS: &str = "mock_status";
+
+/// An error response with `status` and `Retry-After: 0`, so a retry follows
+/// at once.
+pub(crate) fn status(status: u16) -> Value {
+    serde_json::json!({MOCK_STATUS: status, "error": {"message": "mock error"}})
+}
+
+/// A `chat/completions` response with one assistant message.
+pub(crate) fn reply(content: &str, tool_calls: Value) -> Value {
+    serde_json::json!({
+        "choices": [{
+            "message": {"role": "assistant", "content": content, "tool_calls": tool_calls}
+        }],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+    })
+}
+
+/// A native tool call as the provider sends it, arguments JSON-encoded.
+pub(crate) fn tool_call(name: &str, args: Value) -> Value {
+    serde_json::json!({
+        "id": format!("call_{name}"),
+        "type": "function",
+        "function": {"name": name, "arguments": args.to_string()}
+    })
+}
+
+fn read_request_body(reader: &mut impl BufRead) -> Vec<u8> {
+    let mut content_length = 0;
+    let mut line = String::new();
+    while reader.read_line(&mut line).is_ok_and(|n| n > 0) {
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+        line.clear();
+    }
+    let mut body = vec![0; content_length];
+    let _ = reader.read_exact(&mut body);
+    body
+}
@@ -0,0 +1,261 @@
+//! Adapter for Ollama's native `/api/chat` and `/api/tags` endpoints.
+//!
+//! Requests are translated from the OpenAI-shaped [`ChatRequest`] and responses
+//! back into [`ChatResponse`], so callers stay provider-agnostic. Ollama reports
+//! token counts but no pricing, so `Usage::total_cost` is always `None` and the
+//! model catalog has no cost or context-length information.
+
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+    AssistantMessage, ChatRequest, ChatResponse, Choice, Model, Tool, ToolCall, ToolCallFunction,
+    Usage, check_status, read_json, send_with_retry,
+};
+use crate::config::AppConfig;
+
+#[derive(Serialize)]
+struct OllamaChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<OllamaMessage>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<OllamaOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<&'a [Tool]>,
+}
+
+#[derive(Serialize)]
+struct OllamaOptions {
+    temperature: f32,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct OllamaMessage {
+    #[serde(default)]
+    role: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OllamaToolCall>,
+    /// Raw base64 image data; Ollama takes no `data:` prefix.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    images: Vec<String>,
+    #[serde(default, skip_serializing)]
+    thinking: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OllamaToolCall {
+    function: OllamaFunction,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OllamaFunction {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[derive(Deserialize)]
+struct OllamaChatResponse {
+    #[serde(default)]
+    message: OllamaMessage,
+    #[serde(default)]
+    prompt_eval_count: Option<u32>,
+    #[serde(default)]
+    eval_count: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    models: Vec<TagModel>,
+}
+
+#[derive(Deserialize)]
+struct TagModel {
+    name: String,
+}
+
+pub(super) async fn chat(
+    cfg: &AppConfig,
+    client: &Client,
+    body: &ChatRequest,
+    label: &str,
+) -> Result<ChatResponse> {
+    let request = OllamaChatRequest {
+        model: &body.model,
+        messages: body.messages.iter().map(to_ollama_message).collect(),
+        stream: false,
+        options: body
+            .temperature
+            .map(|temperature| OllamaOptions { temperature }),
+        tools: body.tools.as_deref(),
+    };
+    let url = format!("{}/api/chat", cfg.provider.base_url.trim_end_matches('/'));
+    let resp = send_with_retry(client.post(url).json(&request))
+        .await
+        .with_context(|| format!("{label} request failed"))?;
+    let resp: OllamaChatResponse = read_json(check_status(resp, label).await?, label).await?;
+    Ok(from_ollama(resp))
+}
+
+/// Ollama's reply in the OpenAI shape. Tool calls get positional ids, since
+/// Ollama sends none, and object arguments are re-encoded as JSON strings.
+fn from_ollama(resp: OllamaChatResponse) -> ChatResponse {
+    let tool_calls = resp
+        .message
+        .tool_calls
+        .into_iter()
+        .enumerate()
+        .map(|(idx, call)| ToolCall {
+            id: format!("call_{idx}"),
+            r#type: "function".to_string(),
+            function: ToolCallFunction {
+                name: call.function.name,
+                arguments: match call.function.arguments {
+                    Value::String(s) => s,
+                    other => other.to_string(),
+                },
+            },
+        })
+        .collect();
+
+    let usage = (resp.prompt_eval_count.is_some() || resp.eval_count.is_some()).then(|| Usage {
+        prompt_tokens: resp.prompt_eval_count,
+        completion_tokens: resp.eval_count,
+        total_tokens: Some(resp.prompt_eval_count.unwrap_or(0) + resp.eval_count.unwrap_or(0)),
+        total_cost: None,
+    });
+
+    ChatResponse {
+        served_by: None,
+        choices: vec![Choice {
+            message: AssistantMessage {
+                role: "assistant".to_string(),
+                content: resp.message.content,
+                tool_calls,
+                reasoning: resp.message.thinking,
+            },
+        }],
+        usage,
+    }
+}
+
+pub(super) async fn list_models(client: &Client, base_url: &str) -> Result<Vec<Model>> {
+    let resp = send_with_retry(client.get(format!("{base_url}/api/tags")))
+        .await
+        .context("models request failed")?;
+    let resp: TagsResponse = read_json(check_status(resp, "models").await?, "models").await?;
+
+    Ok(resp
+        .models
+        .into_iter()
+        .map(|m| Model {
+            id: m.name.clone(),
+            name: m.name,
+            prompt_cost: None,
+            completion_cost: None,
+            context_length: None,
+        })
+        .collect())
+}
+
+fn to_ollama_message(message: &super::Message) -> OllamaMessage {
+    let tool_calls = message
+        .tool_calls
+        .iter()
+        .flatten()
+        .map(|call| OllamaToolCall {
+            function: OllamaFunction {
+                name: call.function.name.clone(),
+                arguments: serde_json::from_str(&call.function.arguments)
+                    .unwrap_or_else(|_| Value::String(call.function.arguments.clone())),
+            },
+        })
+        .collect();
+    OllamaMessage {
+        role: message.role.clone(),
+        content: message.content.clone(),
+        tool_calls,
+        images: message
+            .images
+            .iter()
+            .map(|url| {
+                url.split_once(";base64,")
+                    .map_or(url.as_str(), |(_, data)| data)
+            })
+            .map(str::to_string)
+            .collect(),
+        thinking: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::Message;
+
+    #[test]
+    fn requests_carry_object_arguments_and_bare_image_data() {
+        let message = Message {
+            role: "assistant".into(),
+            content: "Reading.".into(),
+            tool_calls: Some(vec![ToolCall {
+                id: "call_0".into(),
+                r#type: "function".into(),
+                function: ToolCallFunction {
+                    name: "read".into(),
+                    arguments: r#"{"file_path":"a.rs"}"#.into(),
+                },
+            }]),
+            tool_call_id: None,
+            images: vec!["data:image/png;base64,iVBORw0K".into()],
+        };
+        let json = serde_json::to_value(to_ollama_message(&message)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "role": "assistant",
+                "content": "Reading.",
+                "tool_calls": [{"function": {"name": "read", "arguments": {"file_path": "a.rs"}}}],
+                "images": ["iVBORw0K"]
+            })
+        );
+    }
+
+    #[test]
+    fn responses_translate_to_the_openai_shape() {
+        let resp: OllamaChatResponse = serde_json::from_value(serde_json::json!({
+            "model": "llama3",
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "thinking": "Need the file.",
+                "tool_calls": [{"function": {"name": "read", "arguments": {"file_path": "a.rs"}}}]
+            },
+            "done": true,
+            "prompt_eval_count": 12,
+            "eval_count": 5
+        }))
+        .unwrap();
+        let resp = from_ollama(resp);
+        let message = &resp.choices[0].message;
+        assert_eq!(message.reasoning.as_deref(), Some("Need the file."));
+        assert_eq!(message.tool_calls[0].id, "call_0");
+        assert_eq!(message.tool_calls[0].function.name, "read");
+        assert_eq!(
+            message.tool_calls[0].function.arguments,
+            r#"{"file_path":"a.rs"}"#
+        );
+        let usage = resp.usage.unwrap();
+        assert_eq!(usage.total_tokens, Some(17));
+        assert_eq!(usage.total_cost, None);
+
+        let bare: OllamaChatResponse = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert!(from_ollama(bare).usage.is_none());
+    }
+}
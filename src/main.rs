@@ -1,6 +1,7 @@
 use tracing_subscriber::util::SubscriberInitExt;
 
 use clap::{Parser, Subcommand};
+use std::io::IsTerminal;
 use tracing::Level;
 
 mod agent;
@@ -10,8 +11,14 @@ mod config;
 mod diff;
 mod edits;
 mod fsutil;
+mod git;
+mod history;
 mod llm;
+mod patch;
+mod project;
+mod session;
 mod ui;
+mod util;
 
 #[derive(Parser)]
 #[command(
@@ -24,6 +31,22 @@ struct Cli {
     #[arg(short, long, action=clap::ArgAction::Count)]
     verbose: u8,
 
+    /// Show plans and diffs without writing any files
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Only print warnings, errors and results: no banner or progress output
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Print plain text without ANSI colors (also set by NO_COLOR or piped output)
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Work on the repository at this path instead of the current directory
+    #[arg(long, global = true, value_name = "PATH")]
+    repo: Option<std::path::PathBuf>,
+
     #[command(subcommand)]
     cmd: Commands,
 }
@@ -37,8 +60,43 @@ enum Commands {
         model: Option<String>,
 
         /// Launch the experimental terminal UI
-        #[arg(long)]
+        #[arg(long, conflicts_with = "stdin")]
         tui: bool,
+
+        /// Read all of standard input as a single prompt, handle it and exit
+        #[arg(long)]
+        stdin: bool,
+
+        /// How `--stdin` reports the turn; `json` writes edits without asking
+        #[arg(long, value_enum, default_value_t = chat::Output::Text, requires = "stdin")]
+        output: chat::Output,
+
+        /// With `--stdin`, write edits without asking; needed when stdin is piped
+        #[arg(short = 'y', long, requires = "stdin")]
+        yes: bool,
+
+        /// Put files matching this glob in every prompt's context (repeatable)
+        #[arg(long = "context", value_name = "GLOB")]
+        context: Vec<String>,
+
+        /// Send this image with every prompt; needs provider.multimodal (repeatable)
+        #[arg(long = "image", value_name = "PATH")]
+        image: Vec<std::path::PathBuf>,
+    },
+    /// Apply a unified diff, backing up each file under .smol/backups
+    Apply {
+        /// Patch file, e.g. one written by /export
+        patch: std::path::PathBuf,
+    },
+    /// Print the provider's model catalog
+    Models {
+        /// Emit the catalog as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Only show models whose id contains this substring
+        #[arg(long)]
+        filter: Option<String>,
     },
 }
 
@@ -47,28 +105,142 @@ async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     let level = match cli.verbose {
+        0 if cli.quiet => Level::WARN,
         0 => Level::INFO,
         1 => Level::DEBUG,
         _ => Level::TRACE,
     };
+    let color = util::color_enabled(cli.no_color);
     tracing_subscriber::FmtSubscriber::builder()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .with_max_level(level)
+        .with_ansi(color)
         .finish()
         .init();
+    if !color {
+        inquire::set_global_render_config(inquire::ui::RenderConfig::empty());
+    }
 
+    let repo_root = fsutil::init_repo_root(cli.repo.as_deref())?;
     match cli.cmd {
         Commands::Chat {
             model,
             tui: use_tui,
+            context,
+            image,
+            stdin,
+            output,
+            yes,
         } => {
             if use_tui {
-                ui::run(model).await?;
+                ui::run(repo_root, model, cli.dry_run, cli.quiet, context, image).await?;
             } else {
-                chat::run(model).await?;
+                let one_shot = if stdin {
+                    chat::check_one_shot(std::io::stdin().is_terminal(), yes, cli.dry_run, output)?;
+                    Some(chat::OneShot {
+                        prompt: read_stdin_prompt()?,
+                        output,
+                        yes,
+                    })
+                } else {
+                    None
+                };
+                chat::run(
+                    repo_root,
+                    model,
+                    cli.dry_run,
+                    cli.quiet,
+                    context,
+                    image,
+                    one_shot,
+                )
+                .await?;
             }
         }
+        Commands::Models { json, filter } => {
+            print_models(json, filter.as_deref(), cli.dry_run).await?
+        }
+        Commands::Apply { patch } => apply_patch(&repo_root, &patch, cli.dry_run)?,
+    }
+
+    Ok(())
+}
+
+async fn print_models(json: bool, filter: Option<&str>, dry_run: bool) -> anyhow::Result<()> {
+    let mut cfg = config::load()?;
+    cfg.runtime.dry_run = dry_run;
+    let (mut models, _) = llm::list_models_cached(&cfg, true).await?;
+    if let Some(filter) = filter {
+        let filter = filter.to_lowercase();
+        models.retain(|m| m.id.to_lowercase().contains(&filter));
+    }
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&models)?);
+        return Ok(());
     }
 
+    let width = models.iter().map(|m| m.id.len()).max().unwrap_or(0).max(2);
+    println!(
+        "{:<width$}  {:>8}  {:>10}  {:>10}",
+        "ID", "CONTEXT", "IN $/M", "OUT $/M"
+    );
+    for m in &models {
+        println!(
+            "{:<width$}  {:>8}  {:>10}  {:>10}",
+            m.id,
+            m.context_length.map_or("--".into(), |c| c.to_string()),
+            per_million(m.prompt_cost),
+            per_million(m.completion_cost),
+        );
+    }
     Ok(())
 }
+
+fn apply_patch(
+    repo_root: &std::path::Path,
+    file: &std::path::Path,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    use anyhow::Context;
+    let text = std::fs::read_to_string(file).with_context(|| format!("read {}", file.display()))?;
+    let applied = patch::apply(repo_root, &text, dry_run)?;
+    for a in &applied {
+        let verb = match (dry_run, a.created) {
+            (true, true) => "Would create",
+            (true, false) => "Would patch",
+            (false, true) => "Created",
+            (false, false) => "Patched",
+        };
+        match &a.backup {
+            Some(backup) if !a.created => {
+                println!("{verb} {} (backup: {})", a.path, backup.display())
+            }
+            _ => println!("{verb} {}", a.path),
+        }
+    }
+    if dry_run {
+        println!("Dry run: nothing was written.");
+    }
+    Ok(())
+}
+
+/// `chat --stdin`: the whole of standard input, as one prompt.
+fn read_stdin_prompt() -> anyhow::Result<String> {
+    use anyhow::Context;
+    use std::io::Read;
+    let mut prompt = String::new();
+    std::io::stdin()
+        .read_to_string(&mut prompt)
+        .context("read prompt from stdin")?;
+    let prompt = prompt.trim();
+    if prompt.is_empty() {
+        anyhow::bail!("--stdin was given but standard input is empty");
+    }
+    Ok(prompt.to_string())
+}
+
+fn per_million(cost: Option<f64>) -> String {
+    cost.map_or("--".into(), |c| format!("{:.2}", c * 1_000_000.0))
+}
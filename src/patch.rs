@@ -0,0 +1,323 @@
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::fsutil;
+
+/// One file's worth of hunks from a unified diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePatch {
+    pub path: String,
+    /// The old side is `/dev/null`.
+    pub is_new: bool,
+    pub hunks: Vec<Hunk>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// 1-based line the hunk starts at in the old file.
+    pub old_start: usize,
+    /// Context and removed lines, with their line endings.
+    pub old_lines: Vec<String>,
+    /// Context and added lines, with their line endings.
+    pub new_lines: Vec<String>,
+}
+
+/// What [`apply`] did (or, for a dry run, would do) to one file.
+#[derive(Debug)]
+pub struct Applied {
+    pub path: String,
+    pub created: bool,
+    pub backup: Option<PathBuf>,
+}
+
+/// Parses a unified diff (plain or `git diff` style) into per-file hunks.
+/// Lines outside of `---`/`+++` headers and hunks are ignored.
+pub fn parse(text: &str) -> Result<Vec<FilePatch>> {
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut lines = text.split_inclusive('\n').peekable();
+    let mut old_path: Option<String> = None;
+
+    while let Some(line) = lines.next() {
+        if let Some(rest) = line.strip_prefix("--- ") {
+            old_path = Some(header_path(rest));
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("+++ ") {
+            let old = old_path
+                .take()
+                .context("`+++` header without a preceding `---`")?;
+            let new = header_path(rest);
+            if new == "/dev/null" {
+                anyhow::bail!("patch deletes {old}; deleting files is not supported");
+            }
+            files.push(FilePatch {
+                path: strip_prefix(&new).to_string(),
+                is_new: old == "/dev/null",
+                hunks: Vec::new(),
+            });
+            continue;
+        }
+        if line.starts_with("@@") {
+            let file = files.last_mut().context("hunk before any file header")?;
+            let (old_start, mut old_left, mut new_left) = parse_hunk_header(line)?;
+            let mut hunk = Hunk {
+                old_start,
+                old_lines: Vec::new(),
+                new_lines: Vec::new(),
+            };
+            while old_left > 0 || new_left > 0 {
+                let body = lines
+                    .next()
+                    .with_context(|| format!("truncated hunk in {}", file.path))?;
+                let (tag, content) = body.split_at(body.len().min(1));
+                // Some editors strip the single space from blank context lines.
+                let (tag, content) = if body == "\n" {
+                    (" ", "\n")
+                } else {
+                    (tag, content)
+                };
+                match tag {
+                    " " => {
+                        hunk.old_lines.push(content.to_string());
+                        hunk.new_lines.push(content.to_string());
+                        old_left = old_left.saturating_sub(1);
+                        new_left = new_left.saturating_sub(1);
+                    }
+                    "-" => {
+                        hunk.old_lines.push(content.to_string());
+                        old_left = old_left.saturating_sub(1);
+                    }
+                    "+" => {
+                        hunk.new_lines.push(content.to_string());
+                        new_left = new_left.saturating_sub(1);
+                    }
+                    "\\" => {}
+                    _ => anyhow::bail!("unexpected line in hunk for {}: {body:?}", file.path),
+                }
+                if let Some(marker) = lines.peek()
+                    && marker.starts_with('\\')
+                {
+                    lines.next();
+                    let last = match tag {
+                        "+" => hunk.new_lines.last_mut(),
+                        "-" => hunk.old_lines.last_mut(),
+                        _ => {
+                            strip_newline(hunk.old_lines.last_mut());
+                            hunk.new_lines.last_mut()
+                        }
+                    };
+                    strip_newline(last);
+                }
+            }
+            file.hunks.push(hunk);
+        }
+    }
+
+    if files.is_empty() {
+        anyhow::bail!("no file headers found; is this a unified diff?");
+    }
+    Ok(files)
+}
+
+/// Applies `patch` to `original`. Each hunk must match the current contents
+/// exactly, though it may have moved up or down; otherwise this returns a
+/// conflict error naming the hunk.
+pub fn apply_file(original: &str, patch: &FilePatch) -> Result<String> {
+    let lines: Vec<&str> = original.split_inclusive('\n').collect();
+    let mut out = String::with_capacity(original.len());
+    let mut cursor = 0;
+
+    for (idx, hunk) in patch.hunks.iter().enumerate() {
+        let expected = if hunk.old_lines.is_empty() {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let pos = find_hunk(&lines, &hunk.old_lines, cursor, expected).with_context(|| {
+            format!(
+                "conflict in {}: hunk {} (@@ -{}) does not match the current file",
+                patch.path,
+                idx + 1,
+                hunk.old_start
+            )
+        })?;
+        out.extend(lines[cursor..pos].iter().copied());
+        out.extend(hunk.new_lines.iter().map(String::as_str));
+        cursor = pos + hunk.old_lines.len();
+    }
+    out.extend(lines[cursor..].iter().copied());
+    Ok(out)
+}
+
+/// Applies every file in `text` under `repo_root`, backing up each target into
+/// one `.smol/backups/<ts>` directory. All files are checked before anything is
+/// written, so a conflict leaves the tree untouched. With `dry_run` nothing is
+/// written at all.
+pub fn apply(repo_root: &Path, text: &str, dry_run: bool) -> Result<Vec<Applied>> {
+    let mut planned = Vec::new();
+    for file in parse(text)? {
+        if fsutil::is_write_blocked(&file.path) {
+            anyhow::bail!("refusing to patch {}", file.path);
+        }
+        let abs = fsutil::ensure_inside_repo(repo_root, Path::new(&file.path))
+            .with_context(|| format!("invalid path {}", file.path))?;
+        let old = match fs::read_to_string(&abs) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err).with_context(|| format!("read {}", abs.display())),
+        };
+        let created = !abs.exists();
+        if file.is_new && !old.is_empty() {
+            anyhow::bail!(
+                "conflict in {}: patch creates it but it already exists",
+                file.path
+            );
+        }
+        let new = apply_file(&old, &file)?;
+        planned.push((file.path, abs, created, new));
+    }
+
+    let backup_root = if dry_run {
+        None
+    } else {
+        Some(fsutil::timestamp_dir()?)
+    };
+    let mut applied = Vec::new();
+    for (path, abs, created, new) in planned {
+        let backup = match &backup_root {
+            Some(root) => {
+                let backup = fsutil::backup_path(root, &abs, repo_root)?;
+                fsutil::backup_and_write(&abs, &new, &backup)?;
+                Some(backup)
+            }
+            None => None,
+        };
+        applied.push(Applied {
+            path,
+            created,
+            backup,
+        });
+    }
+    Ok(applied)
+}
+
+/// Start index of `old` in `lines` at or after `from`, preferring the match
+/// closest to `expected`.
+fn find_hunk(lines: &[&str], old: &[String], from: usize, expected: usize) -> Option<usize> {
+    let last = lines.len().checked_sub(old.len())?;
+    (from..=last)
+        .filter(|&pos| old.iter().zip(&lines[pos..]).all(|(a, b)| a == b))
+        .min_by_key(|&pos| pos.abs_diff(expected))
+}
+
+fn parse_hunk_header(line: &str) -> Result<(usize, usize, usize)> {
+    let bad = || anyhow::anyhow!("malformed hunk header: {}", line.trim_end());
+    let mut parts = line.split_whitespace().skip(1);
+    let old = parts
+        .next()
+        .and_then(|p| p.strip_prefix('-'))
+        .ok_or_else(bad)?;
+    let new = parts
+        .next()
+        .and_then(|p| p.strip_prefix('+'))
+        .ok_or_else(bad)?;
+    let range = |spec: &str| -> Option<(usize, usize)> {
+        match spec.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((spec.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_count) = range(old).ok_or_else(bad)?;
+    let (_, new_count) = range(new).ok_or_else(bad)?;
+    Ok((old_start, old_count, new_count))
+}
+
+/// Path from a `---`/`+++` header, dropping any trailing timestamp.
+fn header_path(rest: &str) -> String {
+    let rest = rest.trim_end_matches(['\n', '\r']);
+    rest.split('\t').next().unwrap_or(rest).trim().to_string()
+}
+
+fn strip_prefix(path: &str) -> &str {
+    path.strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path)
+}
+
+fn strip_newline(line: Option<&mut String>) {
+    if let Some(line) = line
+        && line.ends_with('\n')
+    {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edits::{PreparedEdit, to_git_patch};
+
+    fn prepared(path: &str, existed: bool, old: &str, new: &str) -> PreparedEdit {
+        PreparedEdit {
+            path: path.into(),
+            abs_path: PathBuf::from(path),
+            rationale: None,
+            existed,
+            old_contents: old.into(),
+            new_contents: new.into(),
+            stats: crate::diff::LineStats::between(old, new),
+        }
+    }
+
+    #[test]
+    fn round_trips_exported_patches() {
+        let old = "fn a() {}\nfn b() {}\nfn c() {}\n";
+        let new = "fn a() {}\nfn b2() {}\nfn c() {}";
+        let text = to_git_patch(&[
+            prepared("src/lib.rs", true, old, new),
+            prepared("src/new.rs", false, "", "hello\n"),
+        ]);
+        let files = parse(&text).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(apply_file(old, &files[0]).unwrap(), new);
+        assert!(files[1].is_new);
+        assert_eq!(apply_file("", &files[1]).unwrap(), "hello\n");
+    }
+
+    #[test]
+    fn shifted_hunks_apply_and_stale_ones_conflict() {
+        let old = "a\nb\nc\n";
+        let text = to_git_patch(&[prepared("f.txt", true, old, "a\nB\nc\n")]);
+        let file = &parse(&text).unwrap()[0];
+
+        assert_eq!(
+            apply_file("top\na\nb\nc\n", file).unwrap(),
+            "top\na\nB\nc\n"
+        );
+        let err = apply_file("a\nx\nc\n", file).unwrap_err().to_string();
+        assert!(err.contains("conflict in f.txt: hunk 1"), "{err}");
+    }
+
+    #[test]
+    fn apply_checks_every_file_before_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("ok.txt"), "one\n").unwrap();
+        fs::write(dir.path().join("stale.txt"), "changed\n").unwrap();
+        let text = to_git_patch(&[
+            prepared("ok.txt", true, "one\n", "two\n"),
+            prepared("stale.txt", true, "original\n", "new\n"),
+        ]);
+
+        assert!(apply(dir.path(), &text, false).is_err());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("ok.txt")).unwrap(),
+            "one\n"
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! Works out the project's language, and framework where it is obvious, from
+//! the manifests at the repo root, so the model follows its conventions.
+
+use std::{fs, path::Path};
+
+use serde_json::Value as Json;
+use toml::Value as Toml;
+
+/// Dependencies named per manifest before the rest are elided.
+const MAX_DEPS: usize = 10;
+
+/// Checked in order when `package.json` is found; the first one present names
+/// the framework.
+const JS_FRAMEWORKS: &[(&str, &str)] = &[
+    ("next", "Next.js"),
+    ("@angular/core", "Angular"),
+    ("svelte", "Svelte"),
+    ("vue", "Vue"),
+    ("react", "React"),
+    ("express", "Express"),
+];
+
+/// Manifests recognized by name alone, each naming its language.
+const MARKERS: &[(&str, &str)] = &[
+    ("requirements.txt", "Python"),
+    ("Gemfile", "Ruby"),
+    ("pom.xml", "Java (Maven)"),
+    ("build.gradle", "JVM (Gradle)"),
+    ("build.gradle.kts", "JVM (Gradle)"),
+    ("composer.json", "PHP"),
+];
+
+/// One manifest's findings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectType {
+    /// Language, with edition, version or framework when known.
+    pub kind: String,
+    pub deps: Vec<String>,
+}
+
+impl ProjectType {
+    /// `Project type: Rust (edition 2021), deps: anyhow, serde`.
+    pub fn describe(&self) -> String {
+        let mut line = format!("Project type: {}", self.kind);
+        if !self.deps.is_empty() {
+            line.push_str(", deps: ");
+            line.push_str(&self.deps[..self.deps.len().min(MAX_DEPS)].join(", "));
+            if self.deps.len() > MAX_DEPS {
+                line.push_str(", …");
+            }
+        }
+        line
+    }
+}
+
+/// Every recognized manifest at the root of `repo_root`. A manifest that fails
+/// to parse still reports its language, without details.
+pub fn detect(repo_root: &Path) -> Vec<ProjectType> {
+    let read = |name: &str| fs::read_to_string(repo_root.join(name)).ok();
+    let mut found = Vec::new();
+    if let Some(text) = read("Cargo.toml") {
+        found.push(cargo(&text));
+    }
+    if let Some(text) = read("package.json") {
+        found.push(package_json(
+            &text,
+            repo_root.join("tsconfig.json").is_file(),
+        ));
+    }
+    if let Some(text) = read("pyproject.toml") {
+        found.push(pyproject(&text));
+    }
+    if let Some(text) = read("go.mod") {
+        found.push(go_mod(&text));
+    }
+    for (manifest, kind) in MARKERS {
+        let already = found.iter().any(|p| p.kind.starts_with(kind));
+        if !already && repo_root.join(manifest).is_file() {
+            found.push(ProjectType {
+                kind: kind.to_string(),
+                deps: Vec::new(),
+            });
+        }
+    }
+    found
+}
+
+/// What `/info` shows when [`summary`] finds nothing.
+pub const NOT_DETECTED: &str = "Project type: not detected (no Cargo.toml, package.json, pyproject.toml or go.mod at the repo root)";
+
+/// One [`ProjectType::describe`] line per manifest, or `None` when nothing
+/// was recognized.
+pub fn summary(repo_root: &Path) -> Option<String> {
+    let lines: Vec<String> = detect(repo_root)
+        .iter()
+        .map(ProjectType::describe)
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+fn cargo(text: &str) -> ProjectType {
+    let manifest: Toml = toml::from_str(text).unwrap_or(Toml::Table(Default::default()));
+    let edition = manifest
+        .get("package")
+        .or_else(|| manifest.get("workspace")?.get("package"))
+        .and_then(|package| package.get("edition")?.as_str());
+    let language = if manifest.get("package").is_none() && manifest.get("workspace").is_some() {
+        "Rust workspace"
+    } else {
+        "Rust"
+    };
+    let kind = match edition {
+        Some(edition) => format!("{language} (edition {edition})"),
+        None => language.to_string(),
+    };
+    ProjectType {
+        kind,
+        deps: toml_keys(manifest.get("dependencies")),
+    }
+}
+
+fn package_json(text: &str, has_tsconfig: bool) -> ProjectType {
+    let manifest: Json = serde_json::from_str(text).unwrap_or(Json::Null);
+    let mut deps = json_keys(manifest.get("dependencies"));
+    deps.extend(json_keys(manifest.get("devDependencies")));
+    let language = if has_tsconfig || deps.iter().any(|d| d == "typescript") {
+        "TypeScript"
+    } else {
+        "JavaScript"
+    };
+    let kind = match JS_FRAMEWORKS
+        .iter()
+        .find(|(dep, _)| deps.iter().any(|d| d == dep))
+    {
+        Some((_, framework)) => format!("{language} ({framework})"),
+        None => language.to_string(),
+    };
+    ProjectType { kind, deps }
+}
+
+fn pyproject(text: &str) -> ProjectType {
+    let manifest: Toml = toml::from_str(text).unwrap_or(Toml::Table(Default::default()));
+    let project = manifest.get("project");
+    let kind = match project.and_then(|p| p.get("requires-python")?.as_str()) {
+        Some(version) => format!("Python ({version})"),
+        None => "Python".to_string(),
+    };
+    let deps = match project.and_then(|p| p.get("dependencies")?.as_array()) {
+        Some(specs) => specs
+            .iter()
+            .filter_map(Toml::as_str)
+            .map(requirement_name)
+            .collect(),
+        None => {
+            let poetry = manifest
+                .get("tool")
+                .and_then(|tool| tool.get("poetry")?.get("dependencies"));
+            toml_keys(poetry)
+                .into_iter()
+                .filter(|name| name != "python")
+                .collect()
+        }
+    };
+    ProjectType { kind, deps }
+}
+
+/// `requests>=2.31; python_version > "3.8"` → `requests`.
+fn requirement_name(spec: &str) -> String {
+    let end = spec
+        .find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .unwrap_or(spec.len());
+    spec[..end].to_string()
+}
+
+fn go_mod(text: &str) -> ProjectType {
+    let mut version = None;
+    let mut deps = Vec::new();
+    let mut in_require = false;
+    for line in text.lines().map(str::trim) {
+        if let Some(v) = line.strip_prefix("go ") {
+            version = Some(v.trim());
+        } else if line.starts_with("require (") {
+            in_require = true;
+        } else if in_require && line == ")" {
+            in_require = false;
+        } else if let Some(module) = line.strip_prefix("require ").or(in_require.then_some(line))
+            && let Some(path) = module.split_whitespace().next()
+            && !path.starts_with("//")
+        {
+            deps.push(path.to_string());
+        }
+    }
+    ProjectType {
+        kind: match version {
+            Some(version) => format!("Go {version}"),
+            None => "Go".to_string(),
+        },
+        deps,
+    }
+}
+
+fn toml_keys(table: Option<&Toml>) -> Vec<String> {
+    table
+        .and_then(Toml::as_table)
+        .map(|table| table.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+fn json_keys(object: Option<&Json>) -> Vec<String> {
+    object
+        .and_then(Json::as_object)
+        .map(|object| object.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_manifests_with_versions_and_deps() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(
+            root.join("Cargo.toml"),
+            "[package]\nname = \"x\"\nedition = \"2021\"\n\n[dependencies]\nserde = \"1\"\nanyhow = \"1\"\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("package.json"),
+            r#"{"dependencies":{"react":"^18"},"devDependencies":{"typescript":"^5"}}"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("go.mod"),
+            "module example.com/x\n\ngo 1.22\n\nrequire (\n\tgithub.com/spf13/cobra v1.8.0\n)\n",
+        )
+        .unwrap();
+
+        let lines: Vec<String> = detect(root).iter().map(ProjectType::describe).collect();
+        assert_eq!(
+            lines,
+            [
+                "Project type: Rust (edition 2021), deps: anyhow, serde",
+                "Project type: TypeScript (React), deps: react, typescript",
+                "Project type: Go 1.22, deps: github.com/spf13/cobra",
+            ]
+        );
+    }
+
+    #[test]
+    fn pyproject_dependencies_are_reduced_to_names() {
+        let python = pyproject(
+            "[project]\nrequires-python = \">=3.10\"\ndependencies = [\"requests>=2.31\", \"rich[jupyter]\"]\n",
+        );
+        assert_eq!(
+            python.describe(),
+            "Project type: Python (>=3.10), deps: requests, rich"
+        );
+        assert_eq!(summary(tempfile::tempdir().unwrap().path()), None);
+    }
+}
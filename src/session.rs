@@ -0,0 +1,282 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{agent, fsutil};
+
+/// One line of a session transcript (`.smol/sessions/<timestamp>.jsonl`).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionTurn {
+    pub timestamp: u64,
+    pub prompt: String,
+    #[serde(default)]
+    pub plan: Vec<String>,
+    #[serde(default)]
+    pub reads: Vec<String>,
+    #[serde(default)]
+    pub creates: Vec<String>,
+    pub response: String,
+    /// The memory entry recorded for this turn, so a transcript can be replayed
+    /// into the conversation memory.
+    pub summary: String,
+}
+
+impl SessionTurn {
+    pub fn new(prompt: &str, outcome: &agent::AgentOutcome, summary: &str) -> Self {
+        Self {
+            timestamp: unix_now(),
+            prompt: prompt.to_string(),
+            plan: outcome
+                .plan
+                .iter()
+                .map(|step| step.description.clone())
+                .collect(),
+            reads: outcome.reads.iter().map(agent::format_read_log).collect(),
+            creates: outcome
+                .creates
+                .iter()
+                .map(agent::format_create_log)
+                .collect(),
+            response: outcome.response.content.clone(),
+            summary: summary.to_string(),
+        }
+    }
+}
+
+pub struct Session {
+    path: PathBuf,
+    pending: Vec<SessionTurn>,
+    saved: usize,
+}
+
+impl Session {
+    pub fn new() -> Result<Self> {
+        Ok(Self::at(
+            sessions_dir()?.join(format!("{}.jsonl", unix_now())),
+        ))
+    }
+
+    fn at(path: PathBuf) -> Self {
+        Self {
+            path,
+            pending: Vec::new(),
+            saved: 0,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn saved_turns(&self) -> usize {
+        self.saved
+    }
+
+    /// Flushes the transcript and copies it to `<name>.jsonl` next to it, so
+    /// it can be found again with `/load <name>`. Returns the copy's path.
+    pub fn save_as(&mut self, name: &str) -> Result<PathBuf> {
+        let plain = Path::new(name)
+            .components()
+            .eq([std::path::Component::Normal(name.as_ref())]);
+        if !plain || name.starts_with('.') {
+            anyhow::bail!("{name:?} is not a valid session name");
+        }
+        self.flush()?;
+        if self.saved == 0 {
+            anyhow::bail!("nothing to save yet");
+        }
+        let target = self.path.with_file_name(format!("{name}.jsonl"));
+        fs::copy(&self.path, &target).with_context(|| format!("write {}", target.display()))?;
+        Ok(target)
+    }
+
+    /// Queues the turn and appends it to the transcript file.
+    pub fn record(&mut self, turn: SessionTurn) -> Result<()> {
+        self.pending.push(turn);
+        self.flush()
+    }
+
+    /// Appends every turn that has not been written yet. Turns stay queued if the
+    /// write fails so a later flush can retry them.
+    pub fn flush(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).context("create sessions dir")?;
+        }
+        let mut out = String::new();
+        for turn in &self.pending {
+            out.push_str(&serde_json::to_string(turn).context("serialize session turn")?);
+            out.push('\n');
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("open {}", self.path.display()))?;
+        file.write_all(out.as_bytes())
+            .with_context(|| format!("write {}", self.path.display()))?;
+        self.saved += self.pending.len();
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+pub struct LoadedSession {
+    pub path: PathBuf,
+    pub turns: Vec<SessionTurn>,
+    pub skipped: usize,
+}
+
+impl LoadedSession {
+    /// The memory entries of the most recent `limit` turns, oldest first.
+    pub fn memory(&self, limit: usize) -> Vec<String> {
+        let start = self.turns.len().saturating_sub(limit);
+        self.turns[start..]
+            .iter()
+            .map(|turn| turn.summary.clone())
+            .collect()
+    }
+
+    pub fn describe(&self, limit: usize) -> String {
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        let mut text = format!(
+            "Loaded {} of {} turns from {name}",
+            self.turns.len().min(limit),
+            self.turns.len()
+        );
+        if let Some(last) = self.turns.last() {
+            text.push_str(&format!(" (last prompt: {})", last.prompt));
+        }
+        text
+    }
+}
+
+/// Reads a saved transcript. `name` may be a file in `.smol/sessions` (with or
+/// without the `.jsonl` extension) or a path. Malformed lines are skipped and
+/// counted rather than failing the whole load.
+pub fn load(name: &str) -> Result<LoadedSession> {
+    let path = resolve_session(name)?;
+    let text = fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+    let mut turns = Vec::new();
+    let mut skipped = 0;
+    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+        match serde_json::from_str::<SessionTurn>(line) {
+            Ok(turn) => turns.push(turn),
+            Err(_) => skipped += 1,
+        }
+    }
+    if turns.is_empty() {
+        anyhow::bail!("no valid turns in {}", path.display());
+    }
+    Ok(LoadedSession {
+        path,
+        turns,
+        skipped,
+    })
+}
+
+fn resolve_session(name: &str) -> Result<PathBuf> {
+    let dir = sessions_dir()?;
+    let candidates = [
+        dir.join(name),
+        dir.join(format!("{name}.jsonl")),
+        PathBuf::from(name),
+    ];
+    candidates
+        .into_iter()
+        .find(|p| p.is_file())
+        .ok_or_else(|| anyhow::anyhow!("session not found: {name}"))
+}
+
+/// `.smol/sessions`; not created until a transcript is flushed.
+pub fn sessions_dir() -> Result<PathBuf> {
+    Ok(fsutil::smol_dir()?.join("sessions"))
+}
+
+/// Saved transcripts, oldest first.
+pub fn list_sessions() -> Result<Vec<PathBuf>> {
+    let dir = sessions_dir()?;
+    let mut sessions = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+            .collect::<Vec<_>>(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err).with_context(|| format!("read {}", dir.display())),
+    };
+    sessions.sort();
+    Ok(sessions)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(prompt: &str) -> SessionTurn {
+        SessionTurn {
+            timestamp: 1,
+            prompt: prompt.into(),
+            plan: Vec::new(),
+            reads: Vec::new(),
+            creates: Vec::new(),
+            response: "[]".into(),
+            summary: format!("User: {prompt}"),
+        }
+    }
+
+    #[test]
+    fn load_skips_malformed_lines_and_keeps_the_latest_memory() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("1.jsonl");
+        let mut text = String::new();
+        for prompt in ["one", "two", "three"] {
+            text.push_str(&serde_json::to_string(&turn(prompt)).unwrap());
+            text.push_str("\n{not json\n");
+        }
+        fs::write(&path, text).unwrap();
+
+        let loaded = load(path.to_str().unwrap()).unwrap();
+        assert_eq!(loaded.turns.len(), 3);
+        assert_eq!(loaded.skipped, 3);
+        assert_eq!(loaded.memory(2), ["User: two", "User: three"]);
+        assert!(loaded.describe(2).ends_with("(last prompt: three)"));
+
+        fs::write(&path, "{not json\n").unwrap();
+        assert!(load(path.to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn recorded_turns_load_back_and_save_under_a_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut session = Session::at(dir.path().join("sessions/100.jsonl"));
+        assert!(session.save_as("empty").is_err());
+        session.record(turn("add a flag")).unwrap();
+        session.record(turn("document it")).unwrap();
+        assert_eq!(session.saved_turns(), 2);
+
+        let loaded = load(session.path().to_str().unwrap()).unwrap();
+        let prompts: Vec<_> = loaded.turns.iter().map(|t| t.prompt.as_str()).collect();
+        assert_eq!(prompts, ["add a flag", "document it"]);
+        assert_eq!(loaded.memory(10), ["User: add a flag", "User: document it"]);
+
+        let named = session.save_as("flags").unwrap();
+        assert_eq!(named, dir.path().join("sessions/flags.jsonl"));
+        assert_eq!(load(named.to_str().unwrap()).unwrap().turns.len(), 2);
+        assert!(session.save_as("../escape").is_err());
+    }
+}
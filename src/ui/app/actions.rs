@@ -1,6 +1,9 @@
 use anyhow::Result;
 use std::path::PathBuf;
-use tokio::spawn;
+use tokio::{
+    spawn,
+    sync::mpsc::{UnboundedSender, unbounded_channel},
+};
 
 use crate::{agent, config, edits};
 
@@ -20,6 +23,8 @@ pub(super) async fn submit_prompt(app: &mut App) -> Result<()> {
     if trimmed.is_empty() {
         return Ok(());
     }
+    app.prompt_history.push(trimmed);
+    app.recall.reset();
 
     if trimmed.starts_with('/') {
         app.reset_input();
@@ -27,7 +32,7 @@ pub(super) async fn submit_prompt(app: &mut App) -> Result<()> {
         return Ok(());
     }
 
-    if app.cfg.auth.api_key.is_empty() {
+    if app.cfg.provider.kind.requires_api_key() && app.cfg.auth.api_key.is_empty() {
         app.add_message(
             MessageKind::Error,
             "Missing OpenRouter API key. Set OPENROUTER_API_KEY or use plain mode /login.".into(),
@@ -39,31 +44,185 @@ pub(super) async fn submit_prompt(app: &mut App) -> Result<()> {
     app.add_message(MessageKind::User, trimmed.to_string());
     app.history.push(trimmed.to_string());
     app.reset_input();
-    app.awaiting_response = true;
-    app.caret_visible = true;
-
     let cfg = app.cfg.clone();
-    let tx = app.tx.clone();
-    let prompt = trimmed.to_string();
+    dispatch(app, cfg, trimmed.to_string());
+
+    Ok(())
+}
+
+/// Re-sends the last prompt with a slightly higher temperature. The failed
+/// attempt is already in memory, so the model sees it as context.
+pub(super) fn retry_last(app: &mut App) {
+    if app.awaiting_response {
+        app.add_message(
+            MessageKind::Warn,
+            "Still waiting for the last response...".into(),
+        );
+        return;
+    }
+    let Some(prompt) = app.history.last().cloned() else {
+        app.add_message(MessageKind::Info, "Nothing to retry.".into());
+        return;
+    };
+
+    let mut cfg = app.cfg.clone();
+    cfg.runtime.temperature = agent::retry_temperature(cfg.runtime.temperature);
+    app.add_message(
+        MessageKind::User,
+        format!(
+            "{prompt} (retry, temperature {:.1})",
+            cfg.runtime.temperature
+        ),
+    );
+    dispatch(app, cfg, prompt);
+}
+
+fn dispatch(app: &mut App, cfg: config::AppConfig, prompt: String) {
+    app.auto_compacted = false;
+    let repo_root = app.repo_root.clone();
+    if agent::repo_looks_empty(&repo_root) {
+        app.add_message(MessageKind::Info, agent::EMPTY_REPO_HINT.into());
+    }
+    let context = super::state::build_context(
+        &app.repo_root,
+        &app.context_globs,
+        &app.memory,
+        &app.cfg.runtime,
+    )
+    .unwrap_or_default();
+    spawn_request_with_progress(app, |progress| {
+        async_handle_prompt(cfg, repo_root, prompt, context, progress)
+    });
+}
+
+/// `/explain <path>`: asks about one file directly, without planning.
+pub(super) fn explain(app: &mut App, rel: &str) {
+    if app.awaiting_response {
+        app.add_message(
+            MessageKind::Warn,
+            "Still waiting for the last response...".into(),
+        );
+        return;
+    }
+    let (context, read) = match agent::explain_context(&app.repo_root, rel) {
+        Ok(found) => found,
+        Err(err) => {
+            app.add_message(MessageKind::Error, format!("Cannot explain: {err:#}"));
+            return;
+        }
+    };
+    app.add_message(MessageKind::User, format!("/explain {rel}"));
+    if let Some(warning) = agent::injection_warning(&read) {
+        app.add_message(MessageKind::Warn, warning);
+    }
     let repo_root = app.repo_root.clone();
+    let cfg = app.cfg.clone();
+    let rel = rel.to_string();
+
+    spawn_request(app, async move {
+        match agent::explain(&cfg, &repo_root, &rel, context, read).await {
+            Ok(outcome) => AsyncEvent::Edits {
+                prompt: agent::explain_prompt(&rel),
+                batch: edits::EditBatch { edits: vec![] },
+                outcome,
+            },
+            Err(err) => AsyncEvent::Error(format!("{err:#}")),
+        }
+    });
+}
+
+/// `/compact`: replaces memory with a model-written summary. Also run after a
+/// turn once memory passes `runtime.compact_memory_bytes`.
+pub(super) fn compact(app: &mut App) {
+    if app.awaiting_response {
+        app.add_message(
+            MessageKind::Warn,
+            "Still waiting for the last response...".into(),
+        );
+        return;
+    }
+    if app.memory.is_empty() {
+        app.add_message(MessageKind::Info, "Nothing to compact.".into());
+        return;
+    }
+    let cfg = app.cfg.clone();
     let memory = app.memory.clone();
+    spawn_request(app, async move {
+        match agent::compact_memory(&cfg, &memory).await {
+            Ok(compaction) => AsyncEvent::Compacted(compaction),
+            Err(err) => AsyncEvent::Error(format!("Compaction failed: {err:#}")),
+        }
+    });
+}
+
+/// Aborts the in-flight request (`Esc` while waiting). Its result, if it
+/// still arrives, is dropped by [`App::handle_async`].
+pub(super) fn cancel_request(app: &mut App) {
+    let Some((_, handle)) = app.in_flight.take() else {
+        return;
+    };
+    handle.abort();
+    app.awaiting_response = false;
+    app.active_step = None;
+    app.request_started = None;
+    app.caret_visible = true;
+    app.add_message(MessageKind::Warn, "Request cancelled.".into());
+}
+
+/// Runs `request` in the background and tags its result with a fresh id, so
+/// only the latest request can update the app.
+fn spawn_request(app: &mut App, request: impl Future<Output = AsyncEvent> + Send + 'static) {
+    spawn_request_with_progress(app, |_| request);
+}
 
-    spawn(async move {
-        let event = async_handle_prompt(cfg, repo_root, prompt, memory).await;
-        let _ = tx.send(event);
+/// Like [`spawn_request`], but `request` also gets a sender for
+/// [`agent::Progress`], forwarded to the app under the same id. Every event
+/// sent before `request` finishes arrives ahead of its result.
+fn spawn_request_with_progress<F, Fut>(app: &mut App, request: F)
+where
+    F: FnOnce(UnboundedSender<agent::Progress>) -> Fut,
+    Fut: Future<Output = AsyncEvent> + Send + 'static,
+{
+    begin_request(app);
+    app.request_seq += 1;
+    let id = app.request_seq;
+    let tx = app.tx.clone();
+    let (progress_tx, mut progress_rx) = unbounded_channel();
+    let request = request(progress_tx);
+    let task = spawn(async move {
+        // The sender lives in `request`, so forwarding stops once it is done.
+        let forward = async {
+            while let Some(progress) = progress_rx.recv().await {
+                let _ = tx.send((id, AsyncEvent::Progress(progress)));
+            }
+        };
+        let (event, ()) = tokio::join!(request, forward);
+        let _ = tx.send((id, event));
     });
+    app.in_flight = Some((id, task.abort_handle()));
+}
 
-    Ok(())
+fn begin_request(app: &mut App) {
+    app.pending_large = None;
+    app.awaiting_response = true;
+    app.streamed_progress = false;
+    app.active_step = None;
+    app.request_started = Some(std::time::Instant::now());
+    app.spinner_frame = 0;
+    app.caret_visible = true;
+    if let Some(watcher) = &mut app.watcher {
+        watcher.clear();
+    }
 }
 
 async fn async_handle_prompt(
     cfg: config::AppConfig,
     repo_root: PathBuf,
     prompt: String,
-    memory: Vec<String>,
+    context: String,
+    progress: UnboundedSender<agent::Progress>,
 ) -> AsyncEvent {
-    let context = super::state::build_context(&memory).unwrap_or_else(|_| String::new());
-    match agent::run(&cfg, &repo_root, &prompt, context).await {
+    match agent::run(&cfg, &repo_root, &prompt, context, Some(&progress)).await {
         Ok(outcome) => {
             if outcome.is_treated_as_info {
                 let batch = edits::EditBatch { edits: vec![] };
@@ -73,7 +232,7 @@ async fn async_handle_prompt(
                     outcome,
                 }
             } else {
-                match edits::parse_edits(&outcome.response.content) {
+                match outcome.response.edit_batch() {
                     Ok(batch) => AsyncEvent::Edits {
                         prompt,
                         batch,
@@ -88,6 +247,6 @@ async fn async_handle_prompt(
                 }
             }
         }
-        Err(err) => AsyncEvent::Error(err.to_string()),
+        Err(err) => AsyncEvent::Error(format!("{err:#}")),
     }
 }
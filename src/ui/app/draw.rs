@@ -4,59 +4,53 @@ use ratatui::{
     widgets::{Block, Borders, Padding, Paragraph, Wrap},
 };
 
-use super::review::ReviewState;
-use super::state::{App, MessageKind};
-use crate::llm;
-use crate::ui::{
-    app::prompt,
-    theme::{
-        ACTIVITY_BORDER, BANNER_BORDER, BANNER_CAT_EAR, BANNER_CAT_EYE, BANNER_CAT_MOUTH,
-        BANNER_CAT_WHISKER, BANNER_TEXT, STATUS_TEXT, UI_BORDER_TYPE,
-    },
-};
+use super::review::{DiffViewState, ReviewState};
+use super::search;
+use super::state::{App, MessageKind, ModelPickerState};
+use crate::ui::{app::prompt, theme::Theme};
+use crate::{diff, edits, llm};
+
+/// Narrower terminals fall back to the unified diff even in side-by-side mode.
+const SIDE_BY_SIDE_MIN_WIDTH: u16 = 100;
+
+const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
 
 pub(super) fn draw(app: &mut App, frame: &mut Frame) {
-    let prompt_lines = app.textarea.lines().len().max(1).min(10) as u16;
+    let prompt_lines = app.textarea.lines().len().clamp(1, 10) as u16;
     let has_plan = app.current_plan.is_some();
     let has_actions = app.messages.iter().any(|m| m.kind == MessageKind::Tool);
-    let constraints = match (has_plan, has_actions) {
-        (true, true) => vec![
-            Constraint::Length(3),
-            Constraint::Length(5),
-            Constraint::Length(3),
-            Constraint::Min(10),
-            Constraint::Length(prompt_lines + 3),
-            Constraint::Length(2),
-        ],
-        (true, false) => vec![
-            Constraint::Length(3),
-            Constraint::Length(5),
-            Constraint::Min(10),
-            Constraint::Length(prompt_lines + 3),
-            Constraint::Length(2),
-        ],
-        (false, true) => vec![
-            Constraint::Length(3),
-            Constraint::Length(3),
-            Constraint::Min(10),
-            Constraint::Length(prompt_lines + 3),
-            Constraint::Length(2),
-        ],
-        (false, false) => vec![
-            Constraint::Length(3),
-            Constraint::Min(10),
-            Constraint::Length(prompt_lines + 3),
-            Constraint::Length(2),
-        ],
-    };
+    let show_banner = app.cfg.runtime.show_banner && !app.cfg.runtime.quiet;
+    let mut constraints = Vec::with_capacity(6);
+    if show_banner {
+        constraints.push(Constraint::Length(3));
+    }
+    if has_plan {
+        constraints.push(Constraint::Length(5));
+    }
+    if has_actions {
+        constraints.push(Constraint::Length(3));
+    }
+    constraints.extend([
+        Constraint::Min(10),
+        Constraint::Length(prompt_lines + 3),
+        Constraint::Length(2),
+    ]);
     let layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints(&constraints)
         .split(frame.area());
 
     let mut layout_idx = 0;
-    draw_banner(frame, layout[layout_idx]);
-    layout_idx += 1;
+    if show_banner {
+        let text = app
+            .cfg
+            .runtime
+            .banner_text
+            .as_deref()
+            .unwrap_or(DEFAULT_BANNER_TEXT);
+        draw_banner(&app.theme, text, frame, layout[layout_idx]);
+        layout_idx += 1;
+    }
 
     if has_plan {
         render_plan(app, frame, layout[layout_idx]);
@@ -69,13 +63,13 @@ pub(super) fn draw(app: &mut App, frame: &mut Frame) {
 
     let history_layout_idx = layout_idx;
     let (history_area, picker_area) = if app.review.is_none() {
-        if let (Some(models), Some(picker)) = (&app.models, &app.model_picker) {
-            let picker_height = picker_height(models);
+        if let (Some(_), Some(picker)) = (&app.models, &app.model_picker) {
+            let picker_height = picker_height(picker);
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints([Constraint::Min(3), Constraint::Length(picker_height.max(3))])
                 .split(layout[history_layout_idx]);
-            (chunks[0], Some((chunks[1], picker.index)))
+            (chunks[0], Some(chunks[1]))
         } else {
             (layout[history_layout_idx], None)
         }
@@ -84,6 +78,7 @@ pub(super) fn draw(app: &mut App, frame: &mut Frame) {
     };
     layout_idx += 1;
 
+    app.activity_area = history_area;
     let history = render_history(app, history_area.width as usize);
 
     // Always target the most recent message when auto-scroll is enabled
@@ -98,9 +93,9 @@ pub(super) fn draw(app: &mut App, frame: &mut Frame) {
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_type(UI_BORDER_TYPE)
-                .border_style(Style::default().fg(ACTIVITY_BORDER))
-                .title("Activity"),
+                .border_type(app.theme.border_type)
+                .border_style(Style::default().fg(app.theme.activity_border))
+                .title(activity_title(app)),
         )
         .scroll((
             (app.activity_scroll.saturating_mul(2))
@@ -111,11 +106,17 @@ pub(super) fn draw(app: &mut App, frame: &mut Frame) {
         .wrap(Wrap { trim: false });
     frame.render_widget(history_block, history_area);
 
-    if let Some(review) = &app.review {
-        let review_block = render_review(review);
+    if let Some(review) = &mut app.review {
+        let side_by_side = app.side_by_side && history_area.width >= SIDE_BY_SIDE_MIN_WIDTH;
+        let review_block = render_review(review, side_by_side, history_area.width, &app.theme);
         frame.render_widget(review_block, history_area);
-    } else if let (Some((area, selected)), Some(models)) = (picker_area, app.models.as_ref()) {
-        let picker_block = render_model_picker(models, selected);
+    } else if let Some(view) = &app.diff_view {
+        let diff_block = render_diff_view(&app.last_prepared, view, &app.theme);
+        frame.render_widget(diff_block, history_area);
+    } else if let (Some(area), Some(models), Some(picker)) =
+        (picker_area, app.models.as_ref(), app.model_picker.as_ref())
+    {
+        let picker_block = render_model_picker(models, picker, &app.theme);
         frame.render_widget(picker_block, area);
     }
 
@@ -124,11 +125,13 @@ pub(super) fn draw(app: &mut App, frame: &mut Frame) {
     draw_status(app, frame, layout[layout_idx]);
 }
 
-fn draw_banner(frame: &mut Frame, area: Rect) {
+const DEFAULT_BANNER_TEXT: &str = "Smol - a minimal coding agent";
+
+fn draw_banner(theme: &Theme, text: &str, frame: &mut Frame, area: Rect) {
     let block = Block::default()
         .borders(Borders::ALL)
-        .border_type(UI_BORDER_TYPE)
-        .border_style(Style::default().fg(BANNER_BORDER))
+        .border_type(theme.border_type)
+        .border_style(Style::default().fg(theme.banner_border))
         .title("Smol CLI");
     let inner = block.inner(area);
     frame.render_widget(block, area);
@@ -137,20 +140,17 @@ fn draw_banner(frame: &mut Frame, area: Rect) {
     }
 
     let cat_line = Line::from(vec![
-        Span::styled("  (", Style::default().fg(BANNER_TEXT)),
-        Span::styled("=", Style::default().fg(BANNER_CAT_WHISKER)),
-        Span::styled("^", Style::default().fg(BANNER_CAT_EAR)),
-        Span::styled("･", Style::default().fg(BANNER_CAT_EYE)),
-        Span::styled("ω", Style::default().fg(BANNER_CAT_MOUTH)),
-        Span::styled("･", Style::default().fg(BANNER_CAT_EYE)),
-        Span::styled("^", Style::default().fg(BANNER_CAT_EAR)),
-        Span::styled("=", Style::default().fg(BANNER_CAT_WHISKER)),
-        Span::styled(")", Style::default().fg(BANNER_TEXT)),
+        Span::styled("  (", Style::default().fg(theme.banner_text)),
+        Span::styled("=", Style::default().fg(theme.banner_cat_whisker)),
+        Span::styled("^", Style::default().fg(theme.banner_cat_ear)),
+        Span::styled("･", Style::default().fg(theme.banner_cat_eye)),
+        Span::styled("ω", Style::default().fg(theme.banner_cat_mouth)),
+        Span::styled("･", Style::default().fg(theme.banner_cat_eye)),
+        Span::styled("^", Style::default().fg(theme.banner_cat_ear)),
+        Span::styled("=", Style::default().fg(theme.banner_cat_whisker)),
+        Span::styled(")", Style::default().fg(theme.banner_text)),
         Span::raw("  "),
-        Span::styled(
-            "Smol - a minimal coding agent",
-            Style::default().fg(BANNER_TEXT),
-        ),
+        Span::styled(text.to_string(), Style::default().fg(theme.banner_text)),
     ]);
     let lines = vec![cat_line];
     let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false });
@@ -166,6 +166,37 @@ fn draw_status(app: &App, frame: &mut Frame, area: Rect) {
         Span::raw("Model: "),
         Span::styled(&app.cfg.provider.model, Style::default().fg(Color::Cyan)),
     ];
+    if let Some(fallback) = &app.served_by {
+        first_line_spans.push(Span::styled(
+            format!(" (last reply from fallback {fallback})"),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    if let Some(started) = app.request_started {
+        let glyph = SPINNER_FRAMES[app.spinner_frame % SPINNER_FRAMES.len()];
+        first_line_spans.push(Span::styled(
+            format!(
+                "   {glyph} {}s (Esc to cancel)",
+                started.elapsed().as_secs()
+            ),
+            Style::default().fg(Color::Magenta),
+        ));
+    }
+    if app.cfg.runtime.dry_run {
+        first_line_spans.push(Span::styled(
+            "   DRY RUN",
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    if let Some(changed) = app.watcher.as_ref().and_then(|w| w.stale_paths()) {
+        let noun = if changed.len() == 1 { "file" } else { "files" };
+        first_line_spans.push(Span::styled(
+            format!("   ● {} {noun} changed", changed.len()),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::DIM),
+        ));
+    }
 
     if let Some(model) = &app.current_model {
         let spent_cents = app
@@ -197,7 +228,7 @@ fn draw_status(app: &App, frame: &mut Frame, area: Rect) {
         ));
     }
 
-    let icon_style = Style::default().fg(STATUS_TEXT);
+    let icon_style = Style::default().fg(app.theme.status_text);
     let mut second_line_spans = vec![
         Span::styled("⏎", icon_style),
         Span::raw(" send   "),
@@ -251,7 +282,7 @@ fn draw_status(app: &App, frame: &mut Frame, area: Rect) {
     ));
 
     // Add scroll indicator
-    if app.messages.len() > 0 {
+    if !app.messages.is_empty() {
         let total = app.messages.len();
         let current = app.activity_scroll + 1;
         second_line_spans.push(Span::raw(format!("   {}/{}", current.min(total), total)));
@@ -260,7 +291,7 @@ fn draw_status(app: &App, frame: &mut Frame, area: Rect) {
     let lines = vec![Line::from(first_line_spans), Line::from(second_line_spans)];
     let paragraph = Paragraph::new(lines)
         .alignment(Alignment::Left)
-        .style(Style::default().fg(STATUS_TEXT));
+        .style(Style::default().fg(app.theme.status_text));
     frame.render_widget(paragraph, area);
 }
 
@@ -269,11 +300,13 @@ fn render_plan(app: &App, frame: &mut Frame, area: Rect) {
     if let Some(plan) = &app.current_plan {
         lines.push(Line::from(Span::styled(
             "Plan:",
-            Style::default().fg(Color::Gray),
+            Style::default().fg(app.theme.info_text),
         )));
         for (idx, step) in plan.iter().enumerate() {
             let checkbox = if app.completed_steps.get(idx).copied().unwrap_or(false) {
                 "✓"
+            } else if app.active_step == Some(idx) {
+                "▸"
             } else {
                 "□"
             };
@@ -297,7 +330,7 @@ fn render_plan(app: &App, frame: &mut Frame, area: Rect) {
             };
             lines.push(Line::from(Span::styled(
                 content,
-                Style::default().fg(Color::Gray),
+                Style::default().fg(app.theme.info_text),
             )));
         }
     }
@@ -305,8 +338,8 @@ fn render_plan(app: &App, frame: &mut Frame, area: Rect) {
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_type(UI_BORDER_TYPE)
-                .style(Style::default().bg(Color::Black))
+                .border_type(app.theme.border_type)
+                .style(Style::default().bg(app.theme.plan_bg))
                 .title("Plan"),
         )
         .wrap(Wrap { trim: false });
@@ -317,14 +350,11 @@ fn render_actions(app: &App, frame: &mut Frame, area: Rect) {
     let mut lines = Vec::new();
     for message in &app.messages {
         if message.kind == MessageKind::Tool {
-            let style = match message.kind {
-                MessageKind::User => Style::default().fg(Color::Cyan),
-                MessageKind::Warn => Style::default().fg(Color::Yellow),
-                MessageKind::Error => Style::default().fg(Color::Red),
-                MessageKind::Info => Style::default().fg(Color::Gray),
-                MessageKind::Tool => Style::default().fg(Color::DarkGray),
-            };
-            let spans = parse_message(&message.content, style, area.width as usize);
+            let spans = parse_message(
+                &message.content,
+                message.kind.style(&app.theme),
+                area.width as usize,
+            );
             lines.push(Line::from(spans));
         }
     }
@@ -332,7 +362,7 @@ fn render_actions(app: &App, frame: &mut Frame, area: Rect) {
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_type(UI_BORDER_TYPE)
+                .border_type(app.theme.border_type)
                 .title("Actions"),
         )
         .wrap(Wrap { trim: false });
@@ -341,23 +371,57 @@ fn render_actions(app: &App, frame: &mut Frame, area: Rect) {
 
 fn render_history(app: &App, width: usize) -> Vec<Line<'static>> {
     let mut lines = Vec::new();
-    for message in &app.messages {
+    let found = app
+        .search
+        .as_ref()
+        .and_then(|s| Some((s.current?, s.query.as_str())));
+    for (idx, message) in app.messages.iter().enumerate() {
         if message.kind != MessageKind::Tool {
-            let style = match message.kind {
-                MessageKind::User => Style::default().fg(Color::Cyan),
-                MessageKind::Warn => Style::default().fg(Color::Yellow),
-                MessageKind::Error => Style::default().fg(Color::Red),
-                MessageKind::Info => Style::default().fg(Color::Gray),
-                MessageKind::Tool => Style::default().fg(Color::DarkGray),
+            let mut message_lines = match &message.styled {
+                Some(styled) => styled.clone(),
+                None => vec![Line::from(parse_message(
+                    &message.content,
+                    message.kind.style(&app.theme),
+                    width,
+                ))],
             };
-            let spans = parse_message(&message.content, style, width);
-            lines.push(Line::from(spans));
+            if let Some((current, query)) = found
+                && current == idx
+            {
+                message_lines = message_lines
+                    .into_iter()
+                    .map(|line| search::highlight(line, query))
+                    .collect();
+            }
+            lines.extend(message_lines);
             lines.push(Line::from(Span::raw(""))); // Add empty line between messages
         }
     }
     lines
 }
 
+/// Activity pane title, showing the query and match position while searching.
+fn activity_title(app: &App) -> String {
+    let Some(search) = &app.search else {
+        return "Activity".into();
+    };
+    let matches = search::match_indices(app, search);
+    let position = match search
+        .current
+        .and_then(|c| matches.iter().position(|&m| m == c))
+    {
+        Some(pos) => format!("{} of {}", pos + 1, matches.len()),
+        None if search.query.is_empty() => "type to search".into(),
+        None => "no match".into(),
+    };
+    let keys = if search.editing {
+        "Enter keep, Esc close"
+    } else {
+        "n older, N newer, Esc close"
+    };
+    format!("Activity — search: {} ({position}; {keys})", search.query)
+}
+
 fn parse_message(content: &str, base_style: Style, _width: usize) -> Vec<Span<'static>> {
     let mut spans = Vec::new();
     let mut remaining = content;
@@ -384,38 +448,107 @@ fn parse_message(content: &str, base_style: Style, _width: usize) -> Vec<Span<'s
     spans
 }
 
-fn render_review(review: &ReviewState) -> Paragraph<'static> {
+fn render_review(
+    review: &mut ReviewState,
+    side_by_side: bool,
+    width: u16,
+    theme: &Theme,
+) -> Paragraph<'static> {
     let mut lines = Vec::new();
     if let Some(current) = review.current_edit() {
         lines.push(Line::raw(format!(
-            "Reviewing {} ({} of {})",
+            "Reviewing {} ({} of {})  {}",
             current.path,
             review.index + 1,
-            review.edits.len()
+            review.edits.len(),
+            current.stats
         )));
         if let Some(r) = &current.rationale {
             lines.push(Line::raw(format!("Reason: {r}")));
         }
-        lines.push(Line::raw("Press y=apply, n=skip, b=cancel review"));
+        lines.push(Line::raw(
+            "Press y=apply, n=skip, e=edit in $EDITOR, c=copy diff, v=toggle side-by-side, b=cancel review",
+        ));
         lines.push(Line::raw("────────────────────────────────"));
-        lines.extend(current.diff.lines().map(|l| Line::raw(l.to_string())));
+        // Borders and padding take two columns on each side.
+        let columns = side_by_side.then(|| usize::from(width.saturating_sub(4)));
+        lines.extend_from_slice(review.diff_lines(columns));
     }
     Paragraph::new(lines)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_type(UI_BORDER_TYPE)
-                .style(Style::default().bg(Color::Rgb(30, 30, 30)))
+                .border_type(theme.border_type)
+                .style(Style::default().bg(theme.diff_bg))
                 .padding(Padding::uniform(1))
                 .title("Proposed edit"),
         )
         .wrap(Wrap { trim: false })
 }
 
-fn render_model_picker(models: &[llm::Model], selected: usize) -> Paragraph<'static> {
+fn render_diff_view(
+    edits: &[edits::PreparedEdit],
+    view: &DiffViewState,
+    theme: &Theme,
+) -> Paragraph<'static> {
+    let mut lines = vec![
+        Line::raw(format!(
+            "{} files changed — ↑/↓ scroll, c=copy patch, Esc close",
+            edits.len()
+        )),
+        Line::raw("────────────────────────────────"),
+    ];
+    for edit in edits {
+        if let Some(r) = &edit.rationale {
+            lines.push(Line::styled(
+                format!("# {}: {r}", edit.path),
+                Style::default().add_modifier(Modifier::DIM),
+            ));
+        }
+        lines.extend(diff::unified_diff_spans(
+            &edit.old_contents,
+            &edit.new_contents,
+            &edit.path,
+        ));
+        lines.push(Line::raw(""));
+    }
+    Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(theme.border_type)
+                .style(Style::default().bg(theme.diff_bg))
+                .padding(Padding::uniform(1))
+                .title("Last batch diff"),
+        )
+        .scroll((view.scroll, 0))
+        .wrap(Wrap { trim: false })
+}
+
+fn render_model_picker(
+    models: &[llm::Model],
+    picker: &ModelPickerState,
+    theme: &Theme,
+) -> Paragraph<'static> {
     let mut lines = Vec::new();
-    lines.push(Line::raw("Select a model (↑/↓, Enter, Esc)"));
-    let len = models.len();
+    if picker.query.is_empty() {
+        lines.push(Line::raw(
+            "Select a model (↑/↓, type to filter, Enter, Esc)",
+        ));
+    } else {
+        lines.push(Line::raw(format!(
+            "Select a model (↑/↓, Enter, Esc) — filter: {}",
+            picker.query
+        )));
+    }
+    if picker.matches.is_empty() {
+        lines.push(Line::styled(
+            "No matches",
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    let selected = picker.index;
+    let len = picker.matches.len();
     let window = 8usize;
     let mut start = selected.saturating_sub(window / 2);
     if start + window > len {
@@ -427,8 +560,14 @@ fn render_model_picker(models: &[llm::Model], selected: usize) -> Paragraph<'sta
         start = 0;
     }
     let end = (start + window).min(len);
-    for idx in start..end {
-        let model = &models[idx];
+    for (idx, model) in picker
+        .matches
+        .iter()
+        .filter_map(|&m| models.get(m))
+        .enumerate()
+        .take(end)
+        .skip(start)
+    {
         let prefix = if idx == selected { ">" } else { " " };
         let style = if idx == selected {
             Style::default()
@@ -459,14 +598,14 @@ fn render_model_picker(models: &[llm::Model], selected: usize) -> Paragraph<'sta
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_type(UI_BORDER_TYPE)
+                .border_type(theme.border_type)
                 .title("Models"),
         )
         .wrap(Wrap { trim: false })
 }
 
-fn picker_height(models: &[llm::Model]) -> u16 {
-    let len = models.len().min(8);
+fn picker_height(picker: &ModelPickerState) -> u16 {
+    let len = picker.matches.len().clamp(1, 8);
     (len as u16).saturating_add(2)
 }
 
@@ -478,7 +617,7 @@ fn format_ctx(ctx: Option<&u32>) -> String {
 }
 
 fn format_ctx_value(c: u32) -> String {
-    if c % 1000 == 0 {
+    if c.is_multiple_of(1000) {
         format!("{}K", c / 1000)
     } else {
         format!("{:.1}K", c as f32 / 1000.0)
@@ -495,10 +634,9 @@ fn format_cost(cost: Option<f64>) -> String {
 }
 
 fn estimate_cost_cents(usage: &llm::Usage, model: &llm::Model) -> Option<f64> {
-    let prompt_tokens = usage.prompt_tokens? as f64;
-    let completion_tokens = usage.completion_tokens.unwrap_or(0) as f64;
-    let prompt_rate = model.prompt_cost?;
-    let completion_rate = model.completion_cost?;
-    let total_cost = prompt_tokens * prompt_rate + completion_tokens * completion_rate;
-    Some(total_cost * 100.0)
+    let estimate = llm::Usage {
+        total_cost: None,
+        ..usage.clone()
+    };
+    estimate.cost(Some(model)).map(|dollars| dollars * 100.0)
 }
@@ -1,10 +1,24 @@
 use anyhow::Result;
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+use ratatui::{
+    style::{Modifier, Style},
+    text::Line,
+};
 use tui_textarea::Input;
 
-use crate::{config, llm, ui::app::prompt};
+use crate::{
+    agent, answer, config, edits, fsutil, llm, session,
+    ui::{
+        app::prompt,
+        theme::{THEMES, Theme},
+    },
+    util,
+};
 
-use super::state::{App, MessageKind, ModelPickerState, WELCOME_MSG};
+use super::{
+    review::DiffViewState,
+    state::{App, MessageKind, ModelPickerState},
+};
 
 pub(super) async fn on_key(app: &mut App, key: KeyEvent) -> Result<()> {
     app.caret_visible = true;
@@ -13,25 +27,41 @@ pub(super) async fn on_key(app: &mut App, key: KeyEvent) -> Result<()> {
         return Ok(());
     }
 
-    if key.code == KeyCode::Tab && key.modifiers.is_empty() {
-        if prompt::try_accept_suggestion(app) {
-            return Ok(());
-        }
+    if key.code == KeyCode::Tab && key.modifiers.is_empty() && prompt::try_accept_suggestion(app) {
+        return Ok(());
     }
 
     if app.review.is_some() {
-        match key.code {
-            KeyCode::Char('y') => {
-                if let Err(err) = app.apply_current() {
-                    app.add_message(MessageKind::Error, format!("Apply failed: {err}"));
-                }
+        if app.keymap.apply.matches(&key) {
+            if let Err(err) = app.apply_current() {
+                app.add_message(MessageKind::Error, format!("Apply failed: {err}"));
             }
-            KeyCode::Char('n') => {
-                app.skip_current("Skipped by user");
-            }
-            KeyCode::Char('b') => {
-                app.review = None;
-                app.add_message(MessageKind::Info, "Exited review.".into());
+        } else if app.keymap.skip.matches(&key) {
+            app.skip_current("Skipped by user");
+        } else if app.keymap.cancel_review.matches(&key) {
+            app.finish_review("Exited review.");
+        } else if key.code == KeyCode::Char('c') {
+            app.copy_current_diff();
+        } else if key.code == KeyCode::Char('v') {
+            app.side_by_side = !app.side_by_side;
+        } else if key.code == KeyCode::Char('e')
+            && let Some(review) = app.review.as_mut()
+        {
+            review.edit_requested = true;
+        }
+        return Ok(());
+    }
+
+    if let Some(view) = app.diff_view.as_mut() {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => view.scroll = view.scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => view.scroll = view.scroll.saturating_add(1),
+            KeyCode::PageUp => view.scroll = view.scroll.saturating_sub(10),
+            KeyCode::PageDown => view.scroll = view.scroll.saturating_add(10),
+            KeyCode::Home => view.scroll = 0,
+            KeyCode::Char('c') => app.copy_combined_diff(),
+            KeyCode::Esc | KeyCode::Char('q') => {
+                app.diff_view = None;
                 app.caret_visible = true;
             }
             _ => {}
@@ -41,18 +71,22 @@ pub(super) async fn on_key(app: &mut App, key: KeyEvent) -> Result<()> {
 
     if let (Some(picker), Some(models)) = (app.model_picker.as_mut(), app.models.as_ref()) {
         match key.code {
-            KeyCode::Up => {
-                if picker.index > 0 {
-                    picker.index -= 1;
-                }
+            KeyCode::Up if picker.index > 0 => {
+                picker.index -= 1;
             }
-            KeyCode::Down => {
-                if picker.index + 1 < models.len() {
-                    picker.index += 1;
-                }
+            KeyCode::Down if picker.index + 1 < picker.matches.len() => {
+                picker.index += 1;
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                picker.query.push(c);
+                picker.refilter(models);
+            }
+            KeyCode::Backspace => {
+                picker.query.pop();
+                picker.refilter(models);
             }
             KeyCode::Enter => {
-                if let Some(model) = models.get(picker.index) {
+                if let Some(model) = picker.selected(models) {
                     app.cfg.provider.model = model.id.clone();
                     config::save(&app.cfg)?;
                     app.current_model = Some(model.clone());
@@ -84,18 +118,56 @@ pub(super) async fn on_key(app: &mut App, key: KeyEvent) -> Result<()> {
         return Ok(());
     }
 
-    if key.code == KeyCode::Enter
-        && (key.modifiers.contains(KeyModifiers::SHIFT)
-            || key.modifiers.contains(KeyModifiers::ALT)
-            || key.modifiers.contains(KeyModifiers::CONTROL))
-    {
+    if super::search::on_key(app, key) {
+        return Ok(());
+    }
+
+    if key.code == KeyCode::Char('r') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        super::search::start(app);
+        return Ok(());
+    }
+
+    if key.code == KeyCode::Esc && app.awaiting_response {
+        super::actions::cancel_request(app);
+        return Ok(());
+    }
+
+    if app.keymap.submit.matches(&key) {
+        app.submit_prompt().await?;
+        return Ok(());
+    }
+
+    // Any Enter chord that is not the submit key inserts a newline.
+    if key.code == KeyCode::Enter {
         app.textarea.insert_newline();
         app.caret_visible = true;
         return Ok(());
     }
 
-    if key.code == KeyCode::Char('z') && key.modifiers.contains(KeyModifiers::CONTROL) {
-        app.undo_last();
+    if app.keymap.undo.matches(&key) {
+        app.undo_last(1);
+        return Ok(());
+    }
+
+    if key.code == KeyCode::Char('y') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        app.redo_last();
+        return Ok(());
+    }
+
+    if key.modifiers.contains(KeyModifiers::CONTROL)
+        && matches!(key.code, KeyCode::Char('p') | KeyCode::Char('n'))
+    {
+        let entries = app.prompt_history.entries();
+        let recalled = if key.code == KeyCode::Char('p') {
+            let current = app.textarea.lines().join("\n");
+            app.recall.older(entries, &current)
+        } else {
+            app.recall.newer(entries)
+        };
+        if let Some(text) = recalled {
+            app.reset_input();
+            app.textarea.insert_str(text);
+        }
         return Ok(());
     }
 
@@ -142,16 +214,127 @@ pub(super) async fn on_key(app: &mut App, key: KeyEvent) -> Result<()> {
         }
     }
 
-    if key.code == KeyCode::Enter && key.modifiers.is_empty() {
-        app.submit_prompt().await?;
-        return Ok(());
-    }
-
     let input = Input::from(Event::Key(key));
     app.textarea.input(input);
     Ok(())
 }
 
+fn theme_names() -> String {
+    THEMES
+        .iter()
+        .map(|theme| theme.name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Lines `/open` shows when no range is given.
+const MAX_OPEN_LINES: usize = 400;
+
+/// `/open <path> [start-end]`: shows a file in the activity pane, highlighted
+/// and numbered, without involving the model.
+fn open_file(app: &mut App, cmd: &str) {
+    const USAGE: &str = "Usage: /open <path> [start-end], e.g. /open src/llm.rs 40-80";
+    let mut args = cmd.split_whitespace().skip(1);
+    let Some(path) = args.next() else {
+        app.add_message(MessageKind::Warn, USAGE.into());
+        return;
+    };
+    let range = match args.next().map(agent::LineRange::parse) {
+        None => None,
+        Some(Some(range)) => Some(range),
+        Some(None) => {
+            app.add_message(MessageKind::Warn, USAGE.into());
+            return;
+        }
+    };
+    let text = match agent::open_file(&app.repo_root, path) {
+        Ok(text) => text,
+        Err(err) => {
+            app.add_message(MessageKind::Warn, format!("Cannot open: {err:#}"));
+            return;
+        }
+    };
+    let lines: Vec<&str> = text.lines().collect();
+    let total = lines.len();
+    if total == 0 {
+        app.add_message(MessageKind::Info, format!("{path} is empty."));
+        return;
+    }
+    let (start, end) = match range {
+        Some(range) => range.bounds(total),
+        None => (1, total.min(MAX_OPEN_LINES)),
+    };
+    let code = lines[start - 1..end].join("\n");
+    let header = format!("{path} (lines {start}-{end} of {total})");
+    let mut rendered = vec![Line::styled(
+        header.clone(),
+        Style::default().add_modifier(Modifier::BOLD),
+    )];
+    rendered.extend(answer::highlight_file(path, &code, start));
+    app.add_styled_message(MessageKind::Info, format!("{header}\n{code}"), rendered);
+    if range.is_none() && end < total {
+        app.add_message(
+            MessageKind::Info,
+            format!(
+                "Showing the first {end} lines; /open {path} {}- for the rest.",
+                end + 1
+            ),
+        );
+    }
+}
+
+/// `/use <name> [file]`: puts the expanded template in the prompt box for
+/// editing. `{selection}` is filled from the clipboard when it holds text.
+fn use_template(app: &mut App, cmd: &str) {
+    let mut args = cmd.split_whitespace().skip(1);
+    let Some(name) = args.next() else {
+        let names = template_names(&app.cfg);
+        app.add_message(
+            MessageKind::Info,
+            format!("Usage: /use <name> [file]. Templates: {names}"),
+        );
+        return;
+    };
+    let file = args.next();
+    let wants_selection = app
+        .cfg
+        .templates
+        .get(name)
+        .is_some_and(|text| text.contains("{selection}"));
+    let selection = if wants_selection {
+        clipboard_text(app)
+    } else {
+        None
+    };
+    match app.cfg.expand_template(name, file, selection.as_deref()) {
+        Some(text) => {
+            app.reset_input();
+            app.textarea.insert_str(text);
+        }
+        None => {
+            let names = template_names(&app.cfg);
+            app.add_message(
+                MessageKind::Warn,
+                format!("Unknown template '{name}'. Templates: {names}"),
+            );
+        }
+    }
+}
+
+fn template_names(cfg: &config::AppConfig) -> String {
+    if cfg.templates.is_empty() {
+        return "none (add a [templates] table to config.toml)".into();
+    }
+    cfg.templates.keys().cloned().collect::<Vec<_>>().join(", ")
+}
+
+fn clipboard_text(app: &mut App) -> Option<String> {
+    if app.clipboard.is_none() {
+        app.clipboard = arboard::Clipboard::new().ok();
+    }
+    app.clipboard.as_mut()?.get_text().ok()
+}
+
 fn display_cost(cost: Option<f64>) -> String {
     cost.map(|c| format!("${:.2}/M", c * 1_000_000.0))
         .unwrap_or_else(|| "--".into())
@@ -162,7 +345,7 @@ fn display_ctx(ctx: Option<u32>) -> String {
 }
 
 fn format_ctx_value(c: u32) -> String {
-    if c % 1000 == 0 {
+    if c.is_multiple_of(1000) {
         format!("{}K", c / 1000)
     } else {
         format!("{:.1}K", c as f32 / 1000.0)
@@ -170,29 +353,70 @@ fn format_ctx_value(c: u32) -> String {
 }
 
 pub(super) fn on_paste(app: &mut App, data: String) {
-    if app.review.is_none() {
+    if app.review.is_none() && app.diff_view.is_none() {
         app.textarea.insert_str(&data);
         app.caret_visible = true;
     }
 }
 
+/// Mouse-wheel scrolling over the Activity pane (or the diff view drawn on top
+/// of it). Other mouse events are ignored.
+pub(super) fn on_mouse(app: &mut App, event: MouseEvent) {
+    let over_activity = app.activity_area.contains(ratatui::layout::Position {
+        x: event.column,
+        y: event.row,
+    });
+    if !over_activity {
+        return;
+    }
+    let max_scroll = app.messages.len().saturating_sub(1);
+    match (event.kind, app.diff_view.as_mut()) {
+        (MouseEventKind::ScrollUp, Some(view)) => view.scroll = view.scroll.saturating_sub(3),
+        (MouseEventKind::ScrollDown, Some(view)) => view.scroll = view.scroll.saturating_add(3),
+        (MouseEventKind::ScrollUp, None) => {
+            app.activity_scroll = app.activity_scroll.saturating_sub(1);
+            app.auto_scroll_enabled = false;
+        }
+        (MouseEventKind::ScrollDown, None) => {
+            app.activity_scroll = (app.activity_scroll + 1).min(max_scroll);
+            app.auto_scroll_enabled = false;
+        }
+        _ => {}
+    }
+}
+
 pub(super) async fn handle_command(app: &mut App, input: &str) -> Result<()> {
     app.caret_visible = true;
     match input {
         "/help" => app.add_message(
             MessageKind::Info,
-            "/login  /model  /clear  /undo  /stats  /quit".into(),
+            "/login  /model  /clear  /new  /retry  /explain  /open  /use  /undo  /revert  /redo  /backups  /rollback  /confirm-large  /diff  /diffmode  /theme  /export  /info  /stats  /cost  /tokens  /compact  /load  /quit"
+                .into(),
         ),
         "/quit" | "/exit" => {
             app.should_quit = true;
         }
         "/clear" => {
             app.messages.clear();
+            app.activity_scroll = 0;
+            app.auto_scroll_enabled = true;
+        }
+        "/new" => {
             app.history.clear();
             app.memory.clear();
+            app.current_plan = None;
+            app.completed_steps.clear();
+            app.last_prepared.clear();
+            app.pending_large = None;
+            app.last_usage = None;
+            app.served_by = None;
             app.total_tokens_used = 0;
-            app.add_message(MessageKind::Info, "History cleared.".into());
-            app.add_message(MessageKind::Info, WELCOME_MSG.into());
+            app.session_cost = 0.0;
+            app.add_message(MessageKind::Info, "Started new conversation.".into());
+        }
+        "/info" => {
+            let info = agent::info(&app.cfg, &app.repo_root);
+            app.add_message(MessageKind::Info, info);
         }
         "/stats" => {
             app.add_message(
@@ -200,17 +424,165 @@ pub(super) async fn handle_command(app: &mut App, input: &str) -> Result<()> {
                 format!("Messages: {}", app.history.len()),
             );
         }
-        "/undo" => app.undo_last(),
+        "/cost" => app.add_message(
+            MessageKind::Info,
+            format!(
+                "Session cost: ${:.4} over {} tokens",
+                app.session_cost, app.total_tokens_used
+            ),
+        ),
+        "/compact" => super::actions::compact(app),
+        cmd if cmd.split_whitespace().next() == Some("/use") => use_template(app, cmd),
+        "/tokens" => {
+            let (mut sections, _) = super::state::context_sections(
+                &app.repo_root,
+                &app.context_globs,
+                &app.memory,
+                &app.cfg.runtime,
+            );
+            if let Some(prompt) = app.history.last() {
+                sections.push(("prompt (last)".to_string(), prompt.clone()));
+            }
+            app.add_message(
+                MessageKind::Info,
+                format!(
+                    "Context tokens for the next turn:\n{}\nEstimated at ~4 characters per token; files read during planning are extra.",
+                    agent::token_table(&sections)
+                ),
+            );
+        }
+        cmd if cmd.split_whitespace().next() == Some("/undo") => {
+            match util::parse_undo_count(cmd) {
+                Some(count) => app.undo_last(count),
+                None => app.add_message(MessageKind::Warn, "Usage: /undo [<count>]".into()),
+            }
+        }
+        "/redo" => app.redo_last(),
+        "/revert" => app.revert_last(),
+        "/backups" => match fsutil::list_backup_sets() {
+            Ok(sets) if sets.is_empty() => app.add_message(MessageKind::Info, "No backups yet.".into()),
+            Ok(sets) => {
+                let lines: Vec<String> = sets
+                    .iter()
+                    .map(|(timestamp, files)| format!("{timestamp} ({files} files)"))
+                    .collect();
+                app.add_message(MessageKind::Info, lines.join("\n"));
+            }
+            Err(err) => app.add_message(
+                MessageKind::Error,
+                format!("Failed to list backups: {err:#}"),
+            ),
+        },
+        cmd if cmd.split_whitespace().next() == Some("/rollback") => {
+            match cmd.split_whitespace().nth(1) {
+                Some(timestamp) => app.rollback(timestamp),
+                None => app.add_message(
+                    MessageKind::Warn,
+                    "Usage: /rollback <timestamp> (see /backups)".into(),
+                ),
+            }
+        }
+        "/confirm-large" => match app.pending_large.take() {
+            Some((batch, prompt)) => app.start_batch(batch, &prompt),
+            None => app.add_message(MessageKind::Info, "No held-back edits to confirm.".into()),
+        },
+        "/retry" => super::actions::retry_last(app),
+        "/diffmode" => {
+            app.side_by_side = !app.side_by_side;
+            let mode = if app.side_by_side {
+                "side-by-side (unified below 100 columns)"
+            } else {
+                "unified"
+            };
+            app.add_message(MessageKind::Info, format!("Review diffs are now {mode}."));
+        }
+        cmd if cmd.starts_with("/theme") => match cmd.split_whitespace().nth(1) {
+            Some(name) => match Theme::by_name(name) {
+                Some(theme) => {
+                    app.theme = theme;
+                    app.textarea
+                        .set_style(Style::default().fg(theme.prompt_text));
+                    app.cfg.runtime.theme = theme.name.to_string();
+                    config::save(&app.cfg)?;
+                    app.add_message(MessageKind::Info, format!("Theme set to {}.", theme.name));
+                }
+                None => app.add_message(
+                    MessageKind::Error,
+                    format!("Unknown theme {name}. Choose one of: {}", theme_names()),
+                ),
+            },
+            None => app.add_message(
+                MessageKind::Info,
+                format!(
+                    "Theme: {}. Available: {} (/theme <name> to switch)",
+                    app.theme.name,
+                    theme_names()
+                ),
+            ),
+        },
+        "/diff" => {
+            if app.last_prepared.is_empty() {
+                app.add_message(MessageKind::Info, "No edits from the last turn.".into());
+            } else {
+                app.model_picker = None;
+                app.diff_view = Some(DiffViewState { scroll: 0 });
+                app.caret_visible = false;
+            }
+        }
+        cmd if cmd.starts_with("/export") => match cmd.split_whitespace().nth(1) {
+            Some(_) if app.last_prepared.is_empty() => {
+                app.add_message(MessageKind::Info, "No edits from the last turn.".into())
+            }
+            Some(file) => match edits::export_patch(&app.repo_root, file, &app.last_prepared) {
+                Ok(path) => app.add_message(
+                    MessageKind::Info,
+                    format!("Wrote patch to {}", path.display()),
+                ),
+                Err(err) => app.add_message(MessageKind::Error, format!("Export failed: {err:#}")),
+            },
+            None => app.add_message(MessageKind::Warn, "Usage: /export <file.patch>".into()),
+        },
+        cmd if cmd.split_whitespace().next() == Some("/load") => {
+            match cmd.split_whitespace().nth(1) {
+                Some(name) => match session::load(name) {
+                    Ok(loaded) => {
+                        app.memory = loaded.memory(agent::MEMORY_LIMIT);
+                        app.add_message(MessageKind::Info, loaded.describe(agent::MEMORY_LIMIT));
+                        if loaded.skipped > 0 {
+                            app.add_message(
+                                MessageKind::Warn,
+                                format!("Skipped {} malformed lines.", loaded.skipped),
+                            );
+                        }
+                    }
+                    Err(err) => {
+                        app.add_message(MessageKind::Warn, format!("Could not load session: {err}"))
+                    }
+                },
+                None => app.add_message(
+                    MessageKind::Warn,
+                    "Usage: /load <session-file> (saved under .smol/sessions)".into(),
+                ),
+            }
+        }
         "/login" => app.add_message(
             MessageKind::Warn,
             "Temporarily unsupported here. Run `/login` in classic chat mode.".into(),
         ),
+        cmd if cmd.starts_with("/explain") => match cmd.split_whitespace().nth(1) {
+            Some(path) => super::actions::explain(app, path),
+            None => app.add_message(MessageKind::Warn, "Usage: /explain <path>".into()),
+        },
+        cmd if cmd.split_whitespace().next() == Some("/open") => open_file(app, cmd),
         cmd if cmd.starts_with("/model") => {
             let parts: Vec<_> = cmd.split_whitespace().collect();
-            if parts.len() == 1 {
-                app.add_message(MessageKind::Info, "Fetching models...".into());
-                match llm::list_models(&app.cfg).await {
-                    Ok(models) => {
+            if parts.len() == 1 || parts[1..] == ["refresh"] {
+                let refresh = parts.len() == 2;
+                if refresh || llm::cached_models(&app.cfg).is_none() {
+                    app.add_message(MessageKind::Info, "Fetching models...".into());
+                }
+                match llm::list_models_cached(&app.cfg, refresh).await {
+                    Ok((models, cached)) => {
                         let count = models.len();
                         if count == 0 {
                             app.add_message(
@@ -221,21 +593,26 @@ pub(super) async fn handle_command(app: &mut App, input: &str) -> Result<()> {
                             app.current_model = None;
                             app.model_picker = None;
                         } else {
+                            let source = if cached {
+                                " from cache (/model refresh to refetch)"
+                            } else {
+                                ""
+                            };
                             app.add_message(
                                 MessageKind::Info,
-                                format!("Loaded {count} programming models."),
+                                format!("Loaded {count} programming models{source}."),
                             );
                             app.add_message(
                                 MessageKind::Info,
-                                "Use ↑/↓ to choose, Enter to confirm, Esc to cancel.".into(),
+                                "Use ↑/↓ to choose, type to filter, Enter to confirm, Esc to cancel.".into(),
                             );
                             let current = models
                                 .iter()
                                 .find(|m| m.id == app.cfg.provider.model)
                                 .cloned();
+                            app.model_picker = Some(ModelPickerState::new(&models, ""));
                             app.models = Some(models);
                             app.current_model = current;
-                            app.model_picker = Some(ModelPickerState { index: 0 });
                             app.caret_visible = false;
                         }
                     }
@@ -247,6 +624,52 @@ pub(super) async fn handle_command(app: &mut App, input: &str) -> Result<()> {
                         app.model_picker = None;
                     }
                 }
+            } else if parts[1..] == ["current"] {
+                let id = app.cfg.provider.model.clone();
+                let model = match app.current_model.clone().filter(|m| m.id == id) {
+                    Some(model) => Ok(Some(model)),
+                    None => llm::find_model(&app.cfg, &id).await,
+                };
+                match model {
+                    Ok(Some(model)) => {
+                        app.add_message(MessageKind::Info, model.details());
+                        app.current_model = Some(model);
+                    }
+                    Ok(None) => app.add_message(
+                        MessageKind::Warn,
+                        format!("Model {id} is not in the provider's catalog; no details known."),
+                    ),
+                    Err(e) => app.add_message(
+                        MessageKind::Error,
+                        format!("Failed to fetch models: {}", e),
+                    ),
+                }
+            } else if parts[1] == "search" {
+                let query = parts[2..].join(" ");
+                match &app.models {
+                    Some(models) => {
+                        let picker = ModelPickerState::new(models, &query);
+                        if picker.matches.is_empty() {
+                            app.add_message(
+                                MessageKind::Warn,
+                                format!("No models match '{query}'."),
+                            );
+                            app.model_picker = None;
+                            app.caret_visible = true;
+                        } else {
+                            app.add_message(
+                                MessageKind::Info,
+                                format!("{} models match '{query}'.", picker.matches.len()),
+                            );
+                            app.model_picker = Some(picker);
+                            app.caret_visible = false;
+                        }
+                    }
+                    None => app.add_message(
+                        MessageKind::Info,
+                        "Please run /model first to load the list of models.".into(),
+                    ),
+                }
             } else if parts.len() == 2 {
                 if let Ok(n) = parts[1].parse::<usize>() {
                     if let Some(models) = &app.models {
@@ -276,20 +699,26 @@ pub(super) async fn handle_command(app: &mut App, input: &str) -> Result<()> {
                         );
                     }
                 } else {
-                    app.cfg.provider.model = parts[1].to_string();
+                    app.cfg.provider.model = app.cfg.resolve_model(parts[1]);
                     config::save(&app.cfg)?;
-                    app.current_model = None;
-                    app.add_message(
-                        MessageKind::Info,
-                        format!("Model set to {}", app.cfg.provider.model),
-                    );
+                    app.current_model = app
+                        .models
+                        .as_ref()
+                        .and_then(|models| models.iter().find(|m| m.id == app.cfg.provider.model))
+                        .cloned();
+                    let message = if app.cfg.provider.model == parts[1] {
+                        format!("Model set to {}", app.cfg.provider.model)
+                    } else {
+                        format!("Model set to {} (alias {})", app.cfg.provider.model, parts[1])
+                    };
+                    app.add_message(MessageKind::Info, message);
                 }
                 app.model_picker = None;
                 app.caret_visible = true;
             } else {
                 app.add_message(
                     MessageKind::Warn,
-                    "Usage: /model [<number> | <provider/model>], e.g., grok-4-fast:free".into(),
+                    "Usage: /model [<number> | <provider/model> | <alias> | search <query> | refresh | current], e.g., grok-4-fast:free or sonnet".into(),
                 );
                 app.current_model = None;
                 app.model_picker = None;
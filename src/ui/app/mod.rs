@@ -3,6 +3,7 @@ mod draw;
 mod input;
 mod prompt;
 mod review;
+mod search;
 mod state;
 
 pub use state::{App, AsyncEvent};
@@ -1,5 +1,4 @@
 use super::state::{App, COMMANDS, SuggestionInfo, SuggestionKind, TokenInfo};
-use crate::ui::theme::{PROMPT_BORDER, PROMPT_TEXT, UI_BORDER_TYPE};
 use ratatui::{
     Frame,
     buffer::Buffer,
@@ -12,8 +11,8 @@ use walkdir::WalkDir;
 pub(super) fn draw_prompt(app: &mut App, frame: &mut Frame, area: Rect) {
     let prompt_block = Block::default()
         .borders(Borders::ALL)
-        .border_type(UI_BORDER_TYPE)
-        .border_style(Style::default().fg(PROMPT_BORDER))
+        .border_type(app.theme.border_type)
+        .border_style(Style::default().fg(app.theme.prompt_border))
         .title("Prompt (Enter to submit, Ctrl+C to exit)");
     frame.render_widget(prompt_block.clone(), area);
     let inner = prompt_block.inner(area);
@@ -51,7 +50,8 @@ pub(super) fn draw_prompt(app: &mut App, frame: &mut Frame, area: Rect) {
         '>'
     };
     frame.render_widget(
-        Paragraph::new(format!("{caret_char} ")).style(Style::default().fg(PROMPT_BORDER)),
+        Paragraph::new(format!("{caret_char} "))
+            .style(Style::default().fg(app.theme.prompt_border)),
         sections[0],
     );
     frame.render_widget(app.textarea.widget(), sections[1]);
@@ -64,7 +64,7 @@ pub(super) fn draw_prompt(app: &mut App, frame: &mut Frame, area: Rect) {
         let text = format!("{}: {}", label, info.matches.join("   "));
         if !text.is_empty() {
             let suggestion_para = Paragraph::new(text)
-                .style(Style::default().fg(PROMPT_TEXT))
+                .style(Style::default().fg(app.theme.prompt_text))
                 .wrap(Wrap { trim: true });
             frame.render_widget(suggestion_para, area);
         }
@@ -107,29 +107,26 @@ pub(super) fn draw_prompt(app: &mut App, frame: &mut Frame, area: Rect) {
                 active_token.as_ref(),
             );
 
-            if let Some(info) = suggestion.as_ref() {
-                if let Some(rem) = info
+            if let Some(info) = suggestion.as_ref()
+                && let Some(rem) = info
                     .matches
                     .first()
                     .and_then(|s| s.strip_prefix(&info.token.prefix))
-                {
-                    if !rem.is_empty() {
-                        let x =
-                            sections[1].x + visible_col.min(sections[1].width.saturating_sub(1));
-                        let y =
-                            sections[1].y + visible_row.min(sections[1].height.saturating_sub(1));
-                        let max_width =
-                            sections[1].width.saturating_sub(visible_col).max(0) as usize;
-                        if max_width > 0 {
-                            frame.buffer_mut().set_stringn(
-                                x,
-                                y,
-                                rem,
-                                max_width,
-                                Style::default().fg(PROMPT_TEXT).add_modifier(Modifier::DIM),
-                            );
-                        }
-                    }
+                && !rem.is_empty()
+            {
+                let x = sections[1].x + visible_col.min(sections[1].width.saturating_sub(1));
+                let y = sections[1].y + visible_row.min(sections[1].height.saturating_sub(1));
+                let max_width = sections[1].width.saturating_sub(visible_col) as usize;
+                if max_width > 0 {
+                    frame.buffer_mut().set_stringn(
+                        x,
+                        y,
+                        rem,
+                        max_width,
+                        Style::default()
+                            .fg(app.theme.prompt_text)
+                            .add_modifier(Modifier::DIM),
+                    );
                 }
             }
 
@@ -184,7 +181,7 @@ pub(super) fn try_accept_suggestion(app: &mut App) -> bool {
 fn set_textarea_with_cursor(app: &mut App, lines: Vec<String>, row: usize, col: usize) {
     let mut textarea = TextArea::from(lines);
     textarea.set_placeholder_text("Describe the change you want");
-    textarea.set_style(Style::default().fg(PROMPT_TEXT));
+    textarea.set_style(Style::default().fg(app.theme.prompt_text));
     textarea.move_cursor(CursorMove::Jump(row as u16, col as u16));
     app.textarea = textarea;
     app.view_offset = (0, 0);
@@ -229,12 +226,12 @@ fn token_at_cursor(
     let upto_cursor = &line[..cursor_byte];
     let start_byte = upto_cursor.rfind(marker)?;
 
-    if require_boundary && start_byte > 0 {
-        if let Some(prev) = line[..start_byte].chars().last() {
-            if !is_token_boundary(prev) {
-                return None;
-            }
-        }
+    if require_boundary
+        && start_byte > 0
+        && let Some(prev) = line[..start_byte].chars().last()
+        && !is_token_boundary(prev)
+    {
+        return None;
     }
 
     let prefix = &line[start_byte..cursor_byte];
@@ -291,14 +288,10 @@ fn file_matches(app: &App, prefix: &str) -> Vec<String> {
 }
 
 fn col_to_byte(line: &str, col: usize) -> usize {
-    let mut count = 0;
-    for (idx, _) in line.char_indices() {
-        if count == col {
-            return idx;
-        }
-        count += 1;
-    }
-    line.len()
+    line.char_indices()
+        .nth(col)
+        .map(|(idx, _)| idx)
+        .unwrap_or(line.len())
 }
 
 fn is_token_boundary(ch: char) -> bool {
@@ -1,30 +1,79 @@
-use std::{fs, io::ErrorKind, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Result;
+use ratatui::text::Line;
 
-use crate::fsutil;
+use crate::{
+    diff,
+    edits::{self, PreparedEdit},
+    fsutil::{self, Restored},
+    git,
+};
 
 use super::state::{App, MessageKind};
 
-#[derive(Clone)]
-pub(super) struct PreparedEdit {
-    pub(super) path: String,
-    pub(super) abs_path: PathBuf,
-    pub(super) diff: String,
-    pub(super) rationale: Option<String>,
-    pub(super) new_contents: String,
-}
-
 pub(super) struct ReviewState {
     pub(super) edits: Vec<PreparedEdit>,
     pub(super) index: usize,
     pub(super) backup_root: PathBuf,
+    /// The prompt that produced the batch, for the autocommit message.
+    pub(super) prompt: String,
+    /// Files written so far, committed together once the review ends.
+    pub(super) applied: Vec<PathBuf>,
+    /// Set by `e`; the run loop suspends the TUI and opens the current edit
+    /// in `$EDITOR`.
+    pub(super) edit_requested: bool,
+    /// The current edit's rendered diff, so redraws do not re-diff the file.
+    pub(super) diff_cache: Option<DiffCache>,
+}
+
+/// Rendered diff lines and the layout they were rendered for.
+pub(super) struct DiffCache {
+    index: usize,
+    /// `Some(width)` for side-by-side, `None` for unified.
+    side_by_side: Option<usize>,
+    lines: Vec<Line<'static>>,
+}
+
+/// Read-only view of every diff prepared for the last batch, opened by `/diff`.
+pub(super) struct DiffViewState {
+    pub(super) scroll: u16,
 }
 
 impl ReviewState {
     pub(super) fn current_edit(&self) -> Option<&PreparedEdit> {
         self.edits.get(self.index)
     }
+
+    /// The current edit's diff, side by side in `side_by_side` columns or
+    /// unified. Recomputed only when the edit, the layout or its contents
+    /// change.
+    pub(super) fn diff_lines(&mut self, side_by_side: Option<usize>) -> &[Line<'static>] {
+        let stale = self
+            .diff_cache
+            .as_ref()
+            .is_none_or(|c| c.index != self.index || c.side_by_side != side_by_side);
+        if stale {
+            let lines = match (self.edits.get(self.index), side_by_side) {
+                (None, _) => Vec::new(),
+                (Some(edit), Some(width)) => {
+                    diff::side_by_side(&edit.old_contents, &edit.new_contents, width)
+                }
+                (Some(edit), None) => {
+                    diff::inline_diff(&edit.old_contents, &edit.new_contents, &edit.path)
+                }
+            };
+            self.diff_cache = Some(DiffCache {
+                index: self.index,
+                side_by_side,
+                lines,
+            });
+        }
+        self.diff_cache.as_ref().map_or(&[], |c| &c.lines)
+    }
 }
 
 pub(super) fn apply_current(app: &mut App) -> Result<()> {
@@ -39,72 +88,281 @@ pub(super) fn apply_current(app: &mut App) -> Result<()> {
 
     let backup_file = fsutil::backup_path(&backup_root, &edit.abs_path, &app.repo_root)?;
     fsutil::backup_and_write(&edit.abs_path, &edit.new_contents, &backup_file)?;
+    app.note_own_write(&edit.abs_path);
     app.add_message(
         MessageKind::Info,
         format!("Applied {} (backup: {})", edit.path, backup_file.display()),
     );
     app.last_backups.push(backup_file);
+    app.redo_stack.clear();
+    if let Some(review) = &mut app.review {
+        review.applied.push(edit.abs_path);
+    }
     advance_review(app);
     Ok(())
 }
 
-pub(super) fn skip_current(app: &mut App, reason: &str) {
-    if let Some(review) = &app.review {
-        if let Some(current) = review.current_edit() {
-            app.add_message(MessageKind::Info, format!("{}: {}", reason, current.path));
+/// The path and proposed contents of the edit `e` asked to open in the
+/// editor, clearing the request.
+pub(super) fn take_external_edit(app: &mut App) -> Option<(String, String)> {
+    let review = app.review.as_mut()?;
+    if !std::mem::take(&mut review.edit_requested) {
+        return None;
+    }
+    let edit = review.current_edit()?;
+    Some((edit.path.clone(), edit.new_contents.clone()))
+}
+
+/// Puts what the user saved in the editor in place of the proposed contents,
+/// so the review shows, and `y` applies, their version.
+pub(super) fn finish_external_edit(app: &mut App, edited: Result<String>) {
+    app.caret_visible = true;
+    let edited = match edited {
+        Ok(edited) => edited,
+        Err(err) => {
+            app.add_message(
+                MessageKind::Warn,
+                format!("Editor failed: {err:#}; keeping the proposed edit."),
+            );
+            return;
         }
+    };
+    let Some(review) = app.review.as_mut() else {
+        return;
+    };
+    let index = review.index;
+    let Some(edit) = review.edits.get_mut(index) else {
+        return;
+    };
+    if edit.new_contents == edited {
+        let path = edit.path.clone();
+        app.add_message(
+            MessageKind::Info,
+            format!("No changes to {path} from the editor."),
+        );
+        return;
+    }
+    edit.set_new_contents(edited.clone());
+    review.diff_cache = None;
+    let message = format!("Using your edit of {}: {}", edit.path, edit.stats);
+    if let Some(last) = app.last_prepared.get_mut(index)
+        && last.abs_path == edit.abs_path
+    {
+        last.set_new_contents(edited);
+    }
+    app.add_message(MessageKind::Info, message);
+}
+
+pub(super) fn skip_current(app: &mut App, reason: &str) {
+    if let Some(review) = &app.review
+        && let Some(current) = review.current_edit()
+    {
+        app.add_message(MessageKind::Info, format!("{}: {}", reason, current.path));
     }
     advance_review(app);
 }
 
+pub(super) fn copy_current_diff(app: &mut App) {
+    let Some(edit) = app.review.as_ref().and_then(|r| r.current_edit()) else {
+        return;
+    };
+    let text = edit.unified_diff();
+    let label = format!("diff for {}", edit.path);
+    copy_to_clipboard(app, text, &label);
+}
+
+pub(super) fn copy_combined_diff(app: &mut App) {
+    if app.last_prepared.is_empty() {
+        return;
+    }
+    let text = edits::combined_diff(&app.last_prepared);
+    let label = format!("combined diff ({} files)", app.last_prepared.len());
+    copy_to_clipboard(app, text, &label);
+}
+
+fn copy_to_clipboard(app: &mut App, text: String, label: &str) {
+    if app.clipboard.is_none() {
+        match arboard::Clipboard::new() {
+            Ok(clipboard) => app.clipboard = Some(clipboard),
+            Err(err) => {
+                app.add_message(MessageKind::Warn, format!("Clipboard unavailable: {err}"));
+                return;
+            }
+        }
+    }
+
+    if let Some(clipboard) = app.clipboard.as_mut() {
+        match clipboard.set_text(text) {
+            Ok(()) => app.add_message(MessageKind::Info, format!("Copied {label} to clipboard.")),
+            Err(err) => app.add_message(MessageKind::Warn, format!("Copy failed: {err}")),
+        }
+    }
+}
+
 fn advance_review(app: &mut App) {
     if let Some(review) = &mut app.review {
         review.index += 1;
         if review.index >= review.edits.len() {
-            app.review = None;
-            app.add_message(MessageKind::Info, "Review complete.".into());
-            app.caret_visible = true;
+            finish_review(app, "Review complete.");
         }
     }
 }
 
-pub(super) fn undo_last(app: &mut App) {
-    if let Some(backup) = app.last_backups.pop() {
-        match super::state::target_from_backup(&app.repo_root, &backup) {
-            Some(target) => {
-                if backup.exists() {
-                    match fs::copy(&backup, &target) {
-                        Ok(_) => app.add_message(
-                            MessageKind::Info,
-                            format!("Reverted {}", target.display()),
-                        ),
-                        Err(err) => {
-                            app.add_message(MessageKind::Error, format!("Undo failed: {err}"))
-                        }
-                    }
-                } else {
-                    match fs::remove_file(&target) {
-                        Ok(_) => app.add_message(
-                            MessageKind::Info,
-                            format!("Removed {}", target.display()),
-                        ),
-                        Err(err) if err.kind() == ErrorKind::NotFound => app.add_message(
-                            MessageKind::Info,
-                            format!("Nothing to undo for {}", target.display()),
-                        ),
-                        Err(err) => {
-                            app.add_message(MessageKind::Error, format!("Undo failed: {err}"))
-                        }
-                    }
-                }
-            }
-            None => app.add_message(
-                MessageKind::Warn,
-                format!("Could not determine target for {}", backup.display()),
-            ),
+/// Closes the review, committing whatever was applied when `git_autocommit`
+/// is on.
+pub(super) fn finish_review(app: &mut App, message: &str) {
+    let Some(review) = app.review.take() else {
+        return;
+    };
+    app.add_message(MessageKind::Info, message.into());
+    app.caret_visible = true;
+    if app.cfg.runtime.git_autocommit && !review.applied.is_empty() {
+        match git::autocommit(&app.repo_root, &review.applied, &review.prompt) {
+            Ok(hash) => app.add_message(MessageKind::Tool, format!("Committed {hash}")),
+            Err(err) => app.add_message(MessageKind::Warn, format!("Skipped git commit: {err:#}")),
         }
-    } else {
+    }
+}
+
+/// Reverts the last `count` applied edits, newest first, stopping early when
+/// the backup stack runs out.
+pub(super) fn undo_last(app: &mut App, count: usize) {
+    app.caret_visible = true;
+    let mut popped = 0;
+    let mut undone = 0;
+    while popped < count {
+        let Some(backup) = app.last_backups.pop_file() else {
+            break;
+        };
+        popped += 1;
+        if restore(app, &backup) {
+            undone += 1;
+        }
+    }
+    if popped == 0 {
         app.add_message(MessageKind::Info, "Nothing to undo.".into());
+    } else if popped < count {
+        app.add_message(
+            MessageKind::Info,
+            format!("Undid {undone} of {count} requested edits; nothing left to undo."),
+        );
+    } else if count > 1 {
+        app.add_message(MessageKind::Info, format!("Undid {undone} edits."));
+    }
+}
+
+/// Restores every file from the most recent batch, deleting files the batch
+/// created. Every target is resolved before any file is touched.
+pub(super) fn revert_last(app: &mut App) {
+    app.caret_visible = true;
+    let Some(batch) = app.last_backups.pop_batch() else {
+        app.add_message(MessageKind::Info, "Nothing to revert.".into());
+        return;
+    };
+    if let Some(bad) = batch
+        .iter()
+        .find(|b| fsutil::target_from_backup(&app.repo_root, b).is_none())
+    {
+        app.add_message(
+            MessageKind::Warn,
+            format!(
+                "Could not determine target for {}; nothing was reverted.",
+                bad.display()
+            ),
+        );
+        app.last_backups.begin_batch();
+        for backup in batch.into_iter().rev() {
+            app.last_backups.push(backup);
+        }
+        return;
+    }
+
+    let total = batch.len();
+    let restored = batch.iter().filter(|b| restore(app, b)).count();
+    app.add_message(
+        MessageKind::Info,
+        format!("Reverted batch: restored {restored} of {total} files."),
+    );
+}
+
+/// Restores every file backed up under `.smol/backups/<timestamp>` and deletes
+/// the files written there that were new.
+pub(super) fn rollback(app: &mut App, timestamp: &str) {
+    app.caret_visible = true;
+    match fsutil::backup_set(timestamp) {
+        Ok(set) => {
+            let total = set.len();
+            let restored = set.iter().filter(|b| restore(app, b)).count();
+            app.add_message(
+                MessageKind::Info,
+                format!("Rolled back {timestamp}: restored {restored} of {total} files."),
+            );
+        }
+        Err(err) => app.add_message(MessageKind::Error, format!("Rollback failed: {err:#}")),
+    }
+}
+
+/// Restores one backup, keeping what it overwrites for `/redo`. Returns
+/// whether the file changed.
+fn restore(app: &mut App, backup: &Path) -> bool {
+    let Some(target) = fsutil::target_from_backup(&app.repo_root, backup) else {
+        app.add_message(
+            MessageKind::Warn,
+            format!("Could not determine target for {}", backup.display()),
+        );
+        return false;
+    };
+    let current = fs::read_to_string(&target).ok();
+    app.note_own_write(&target);
+    let restored = match fsutil::restore_backup(&app.repo_root, backup) {
+        Ok((_, Restored::Reverted)) => {
+            app.add_message(MessageKind::Info, format!("Reverted {}", target.display()));
+            true
+        }
+        Ok((_, Restored::Removed)) => {
+            app.add_message(MessageKind::Info, format!("Removed {}", target.display()));
+            true
+        }
+        Ok((_, Restored::Missing)) => {
+            app.add_message(
+                MessageKind::Info,
+                format!("Nothing to undo for {}", target.display()),
+            );
+            false
+        }
+        Err(err) => {
+            app.add_message(MessageKind::Error, format!("Undo failed: {err:#}"));
+            false
+        }
+    };
+    if restored && let Some(contents) = current {
+        app.redo_stack.push((target, contents));
     }
+    restored
+}
+
+/// Re-applies the contents the last undo replaced. The file is backed up again
+/// so the redo itself can be undone; a file the undo removed is recreated.
+pub(super) fn redo_last(app: &mut App) {
     app.caret_visible = true;
+    let Some((target, contents)) = app.redo_stack.pop() else {
+        app.add_message(MessageKind::Info, "Nothing to redo.".into());
+        return;
+    };
+
+    let result = fsutil::timestamp_dir()
+        .and_then(|root| fsutil::backup_path(&root, &target, &app.repo_root))
+        .and_then(|backup| fsutil::backup_and_write(&target, &contents, &backup).map(|_| backup));
+    match result {
+        Ok(backup) => {
+            app.last_backups.begin_batch();
+            app.last_backups.push(backup);
+            app.note_own_write(&target);
+            app.add_message(MessageKind::Info, format!("Redid {}", target.display()));
+        }
+        Err(err) => {
+            app.add_message(MessageKind::Error, format!("Redo failed: {err}"));
+            app.redo_stack.push((target, contents));
+        }
+    }
 }
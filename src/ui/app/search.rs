@@ -0,0 +1,198 @@
+//! `Ctrl+R` search through the Activity scrollback.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+
+use super::state::{App, Message, MessageKind};
+
+/// Search over Activity messages. While `editing`, typed keys extend the
+/// query and jump as they go; afterwards `n`/`N` step through matches.
+pub(super) struct ActivitySearch {
+    pub(super) query: String,
+    pub(super) editing: bool,
+    /// Index into `App::messages` of the highlighted match.
+    pub(super) current: Option<usize>,
+}
+
+pub(super) fn start(app: &mut App) {
+    app.search = Some(ActivitySearch {
+        query: String::new(),
+        editing: true,
+        current: None,
+    });
+}
+
+/// Handles `key` while a search is open. Returns false when the key closed the
+/// search and should be handled as usual.
+pub(super) fn on_key(app: &mut App, key: KeyEvent) -> bool {
+    let Some(search) = app.search.as_mut() else {
+        return false;
+    };
+    if search.editing {
+        match key.code {
+            KeyCode::Esc => app.search = None,
+            KeyCode::Enter => search.editing = false,
+            KeyCode::Backspace => {
+                search.query.pop();
+                jump(app, Step::Refine);
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                search.query.push(c);
+                jump(app, Step::Refine);
+            }
+            _ => {}
+        }
+        return true;
+    }
+    match key.code {
+        KeyCode::Char('n') => jump(app, Step::Older),
+        KeyCode::Char('N') => jump(app, Step::Newer),
+        KeyCode::Esc => app.search = None,
+        _ => {
+            app.search = None;
+            return false;
+        }
+    }
+    true
+}
+
+enum Step {
+    /// The query changed: stay on the current match if it still matches,
+    /// otherwise look further back.
+    Refine,
+    Older,
+    Newer,
+}
+
+fn jump(app: &mut App, step: Step) {
+    let Some(search) = app.search.as_ref() else {
+        return;
+    };
+    let query = search.query.to_ascii_lowercase();
+    let last = app.messages.len().saturating_sub(1);
+    let found = if query.is_empty() || app.messages.is_empty() {
+        None
+    } else {
+        let hits = |i: &usize| matches(&app.messages[*i], &query);
+        match step {
+            Step::Refine => {
+                let from = search.current.unwrap_or(last);
+                (0..=from).rev().find(hits)
+            }
+            Step::Older => {
+                let from = search.current.unwrap_or(last + 1);
+                (0..from)
+                    .rev()
+                    .find(hits)
+                    .or_else(|| (from..=last).rev().find(hits))
+            }
+            Step::Newer => {
+                let from = search.current.map_or(0, |c| c + 1);
+                (from..=last).find(hits).or_else(|| (0..from).find(hits))
+            }
+        }
+    };
+    if let Some(idx) = found {
+        app.activity_scroll = line_offset(&app.messages, idx) / 2;
+        app.auto_scroll_enabled = false;
+    }
+    if let Some(search) = app.search.as_mut() {
+        search.current = found;
+    }
+}
+
+/// Case-insensitive (ASCII) match against a rendered message.
+fn matches(message: &Message, query_lower: &str) -> bool {
+    message.kind != MessageKind::Tool && message.content.to_ascii_lowercase().contains(query_lower)
+}
+
+/// Indices of every message matching the search, for the "k of n" counter.
+pub(super) fn match_indices(app: &App, search: &ActivitySearch) -> Vec<usize> {
+    let query = search.query.to_ascii_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    (0..app.messages.len())
+        .filter(|&i| matches(&app.messages[i], &query))
+        .collect()
+}
+
+/// First rendered line of `messages[idx]`, mirroring how the Activity pane
+/// lays messages out (tool messages are not shown there).
+fn line_offset(messages: &[Message], idx: usize) -> usize {
+    messages[..idx]
+        .iter()
+        .filter(|m| m.kind != MessageKind::Tool)
+        .map(|m| m.styled.as_ref().map_or(1, Vec::len) + 1)
+        .sum()
+}
+
+/// Re-styles every case-insensitive occurrence of `query` in `line`.
+pub(super) fn highlight(line: Line<'static>, query: &str) -> Line<'static> {
+    if query.is_empty() {
+        return line;
+    }
+    let query = query.to_ascii_lowercase();
+    let mark = Style::default().fg(Color::Black).bg(Color::Yellow);
+    let mut spans = Vec::new();
+    for span in line.spans {
+        let text = span.content.into_owned();
+        let lower = text.to_ascii_lowercase();
+        let mut last = 0;
+        for (start, _) in lower.match_indices(&query) {
+            if start < last {
+                continue;
+            }
+            if start > last {
+                spans.push(Span::styled(text[last..start].to_string(), span.style));
+            }
+            let end = start + query.len();
+            spans.push(Span::styled(
+                text[start..end].to_string(),
+                span.style.patch(mark),
+            ));
+            last = end;
+        }
+        if last < text.len() {
+            spans.push(Span::styled(text[last..].to_string(), span.style));
+        }
+    }
+    Line::from(spans).style(line.style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(kind: MessageKind, content: &str) -> Message {
+        Message {
+            kind,
+            content: content.into(),
+            styled: None,
+        }
+    }
+
+    #[test]
+    fn line_offset_skips_tool_messages() {
+        let messages = vec![
+            message(MessageKind::User, "one"),
+            message(MessageKind::Tool, "hidden"),
+            message(MessageKind::Info, "two"),
+        ];
+        assert_eq!(line_offset(&messages, 2), 2);
+        assert!(!matches(&messages[1], "hidden"));
+        assert!(matches(&messages[2], "two"));
+    }
+
+    #[test]
+    fn highlight_marks_each_match_case_insensitively() {
+        let line = highlight(Line::raw("Config and config"), "CONFIG");
+        let text: Vec<&str> = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(text, vec!["Config", " and ", "config"]);
+        assert_eq!(line.spans[0].style.bg, Some(Color::Yellow));
+        assert_eq!(line.spans[1].style.bg, None);
+    }
+}
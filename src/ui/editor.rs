@@ -0,0 +1,38 @@
+//! Opens a proposed file in the user's editor so an almost-right edit can be
+//! touched up before it is applied.
+
+use std::{fs, path::Path, process::Command};
+
+use anyhow::{Context, Result};
+
+/// `$VISUAL`, then `$EDITOR`, then `vi`. May carry arguments, e.g.
+/// `code --wait`.
+fn editor_command() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|cmd| !cmd.trim().is_empty())
+        .unwrap_or_else(|| "vi".into())
+}
+
+/// Writes `contents` to a temp file named after `path`, so the editor picks
+/// the right syntax, and returns the file as saved once the editor exits.
+pub(super) fn edit(path: &str, contents: &str) -> Result<String> {
+    let dir = tempfile::tempdir().context("create temp dir")?;
+    let name = Path::new(path).file_name().unwrap_or("edit.txt".as_ref());
+    let file = dir.path().join(name);
+    fs::write(&file, contents).with_context(|| format!("write {}", file.display()))?;
+
+    let command = editor_command();
+    let mut words = command.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = Command::new(program)
+        .args(words)
+        .arg(&file)
+        .status()
+        .with_context(|| format!("run {command}"))?;
+    if !status.success() {
+        anyhow::bail!("{command} exited with {status}");
+    }
+    fs::read_to_string(&file).with_context(|| format!("read back {}", file.display()))
+}
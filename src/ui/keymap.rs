@@ -0,0 +1,169 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::config::Keybindings;
+
+/// A single key plus the modifiers that must be held with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    const fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    /// Parses specs like `y`, `enter`, `ctrl+z` or `alt+left`. Named keys and
+    /// modifiers are case-insensitive; a lone character is taken literally
+    /// unless it is chorded with Ctrl or Alt.
+    pub(crate) fn parse(spec: &str) -> Option<Self> {
+        let mut parts: Vec<&str> = spec.trim().split('+').collect();
+        let key = parts.pop()?;
+        let mut modifiers = KeyModifiers::NONE;
+        for m in parts {
+            modifiers |= match m.trim().to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "option" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return None,
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            // Terminals report Ctrl/Alt chords with the lowercase letter.
+            (Some(c), None) if modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                KeyCode::Char(c.to_ascii_lowercase())
+            }
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => {
+                    let n = name.strip_prefix('f')?.parse().ok()?;
+                    if !(1..=12).contains(&n) {
+                        return None;
+                    }
+                    KeyCode::F(n)
+                }
+            },
+        };
+        Some(Self::new(code, modifiers))
+    }
+
+    /// Shift is ignored for character keys, since it is already reflected in
+    /// the character itself.
+    pub(crate) fn matches(&self, key: &KeyEvent) -> bool {
+        let relevant = |m: KeyModifiers| match self.code {
+            KeyCode::Char(_) => m - KeyModifiers::SHIFT,
+            _ => m,
+        };
+        key.code == self.code && relevant(key.modifiers) == relevant(self.modifiers)
+    }
+}
+
+/// Keys for the TUI's rebindable actions, resolved from `[keybindings]`.
+#[derive(Debug, Clone)]
+pub(crate) struct Keymap {
+    pub(crate) apply: KeyBinding,
+    pub(crate) skip: KeyBinding,
+    pub(crate) cancel_review: KeyBinding,
+    pub(crate) undo: KeyBinding,
+    pub(crate) submit: KeyBinding,
+}
+
+impl Keymap {
+    /// Builds the keymap, falling back to the default for any spec that does
+    /// not parse. Returns one warning per rejected spec.
+    pub(crate) fn from_config(bindings: &Keybindings) -> (Self, Vec<String>) {
+        let defaults = Keybindings::default();
+        let mut warnings = Vec::new();
+        let mut resolve = |action: &str, spec: &str, fallback: &str| {
+            KeyBinding::parse(spec).unwrap_or_else(|| {
+                warnings.push(format!(
+                    "Invalid key \"{spec}\" for keybindings.{action}; using \"{fallback}\"."
+                ));
+                KeyBinding::parse(fallback).expect("default keybinding parses")
+            })
+        };
+        let keymap = Self {
+            apply: resolve("apply", &bindings.apply, &defaults.apply),
+            skip: resolve("skip", &bindings.skip, &defaults.skip),
+            cancel_review: resolve(
+                "cancel_review",
+                &bindings.cancel_review,
+                &defaults.cancel_review,
+            ),
+            undo: resolve("undo", &bindings.undo, &defaults.undo),
+            submit: resolve("submit", &bindings.submit, &defaults.submit),
+        };
+        (keymap, warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn parses_and_matches_key_specs() {
+        let undo = KeyBinding::parse("Ctrl+Z").unwrap();
+        assert!(undo.matches(&key(KeyCode::Char('z'), KeyModifiers::CONTROL)));
+        assert!(!undo.matches(&key(KeyCode::Char('z'), KeyModifiers::NONE)));
+
+        let apply = KeyBinding::parse("Y").unwrap();
+        assert!(apply.matches(&key(KeyCode::Char('Y'), KeyModifiers::SHIFT)));
+        assert!(!apply.matches(&key(KeyCode::Char('y'), KeyModifiers::NONE)));
+
+        let submit = KeyBinding::parse("ctrl+enter").unwrap();
+        assert!(submit.matches(&key(KeyCode::Enter, KeyModifiers::CONTROL)));
+        assert!(!submit.matches(&key(KeyCode::Enter, KeyModifiers::NONE)));
+
+        assert_eq!(
+            KeyBinding::parse("f5"),
+            Some(KeyBinding::new(KeyCode::F(5), KeyModifiers::NONE))
+        );
+        for bad in ["", "hyper+x", "f13", "enterr"] {
+            assert!(KeyBinding::parse(bad).is_none(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn invalid_specs_fall_back_to_defaults_with_a_warning() {
+        let bindings = Keybindings {
+            apply: "l".into(),
+            skip: "nope+x".into(),
+            ..Keybindings::default()
+        };
+        let (keymap, warnings) = Keymap::from_config(&bindings);
+        assert!(
+            keymap
+                .apply
+                .matches(&key(KeyCode::Char('l'), KeyModifiers::NONE))
+        );
+        assert!(
+            keymap
+                .skip
+                .matches(&key(KeyCode::Char('n'), KeyModifiers::NONE))
+        );
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("keybindings.skip"));
+    }
+}
@@ -1,5 +1,8 @@
 mod app;
+mod editor;
+mod keymap;
 mod run;
 pub mod theme;
+mod watch;
 
 pub use run::run;
@@ -6,7 +6,8 @@ use std::{
 
 use anyhow::Result;
 use crossterm::{
-    event::{self, Event},
+    cursor,
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
@@ -14,53 +15,120 @@ use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
 
 use crate::config;
 
-use super::app::{App, AsyncEvent};
+use super::{
+    app::{App, AsyncEvent},
+    editor,
+};
 
-pub async fn run(model_override: Option<String>) -> Result<()> {
+pub async fn run(
+    repo_root: PathBuf,
+    model_override: Option<String>,
+    dry_run: bool,
+    quiet: bool,
+    context_globs: Vec<String>,
+    images: Vec<PathBuf>,
+) -> Result<()> {
     let mut cfg = config::load()?;
     if let Some(model) = model_override {
-        cfg.provider.model = model;
+        cfg.provider.model = cfg.resolve_model(&model);
     }
+    cfg.runtime.dry_run = dry_run;
+    cfg.runtime.quiet = quiet;
+    cfg.runtime.images = images;
 
-    let repo_root: PathBuf = std::fs::canonicalize(std::env::current_dir()?)?;
-
+    install_panic_hook();
     enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = ratatui::backend::CrosstermBackend::new(stdout);
+    let res = run_in_terminal(cfg, repo_root, context_globs).await;
+    restore_terminal();
+    res
+}
+
+/// Everything between entering and leaving raw mode, so an early error still
+/// reaches [`restore_terminal`].
+async fn run_in_terminal(
+    cfg: config::AppConfig,
+    repo_root: PathBuf,
+    context_globs: Vec<String>,
+) -> Result<()> {
+    enter_screen(cfg.runtime.mouse_capture)?;
+    let backend = ratatui::backend::CrosstermBackend::new(io::stdout());
     let mut terminal = ratatui::Terminal::new(backend)?;
 
     let (tx, rx) = unbounded_channel();
-    let mut app = App::new(cfg, repo_root, tx);
+    let mut app = App::new(cfg, repo_root, context_globs, tx);
+
+    run_app(&mut terminal, &mut app, rx).await
+}
 
-    let res = run_app(&mut terminal, &mut app, rx).await;
+/// Switches to the alternate screen, capturing the mouse unless
+/// `runtime.mouse_capture` is off.
+fn enter_screen(mouse_capture: bool) -> io::Result<()> {
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    if mouse_capture {
+        execute!(io::stdout(), EnableMouseCapture)?;
+    }
+    Ok(())
+}
 
+/// Leaves raw mode and the alternate screen and shows the cursor. Safe to
+/// call when the terminal is already restored.
+fn restore_terminal() {
     disable_raw_mode().ok();
-    execute!(terminal.backend_mut(), LeaveAlternateScreen).ok();
-    terminal.show_cursor().ok();
+    execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        cursor::Show
+    )
+    .ok();
+}
 
-    res
+/// Hands the terminal back to the shell while `f` runs, e.g. for an external
+/// editor, then takes it over again and redraws from scratch.
+fn suspended<T>(
+    terminal: &mut ratatui::Terminal<ratatui::backend::CrosstermBackend<io::Stdout>>,
+    mouse_capture: bool,
+    f: impl FnOnce() -> T,
+) -> Result<T> {
+    restore_terminal();
+    let result = f();
+    enable_raw_mode()?;
+    enter_screen(mouse_capture)?;
+    terminal.clear()?;
+    Ok(result)
+}
+
+/// Restores the terminal before a panic message is printed, so a crash leaves
+/// the shell usable. The previous hook still runs afterwards.
+fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        previous(info);
+    }));
 }
 
 async fn run_app(
     terminal: &mut ratatui::Terminal<ratatui::backend::CrosstermBackend<io::Stdout>>,
     app: &mut App,
-    mut rx: UnboundedReceiver<AsyncEvent>,
+    mut rx: UnboundedReceiver<(u64, AsyncEvent)>,
 ) -> Result<()> {
     const BLINK_INTERVAL: Duration = Duration::from_millis(500);
     let mut last_blink = Instant::now();
 
     loop {
-        while let Ok(event) = rx.try_recv() {
-            app.handle_async(event);
+        while let Ok((id, event)) = rx.try_recv() {
+            app.handle_async(id, event);
             last_blink = Instant::now();
         }
 
         if last_blink.elapsed() >= BLINK_INTERVAL {
             app.toggle_caret();
+            app.tick_spinner();
             last_blink = Instant::now();
         }
 
+        app.poll_watcher();
         terminal.draw(|frame| app.draw(frame))?;
 
         if app.should_quit() {
@@ -71,6 +139,12 @@ async fn run_app(
             match event::read()? {
                 Event::Key(key) => {
                     app.on_key(key).await?;
+                    if let Some((path, contents)) = app.take_external_edit() {
+                        let edited = suspended(terminal, app.mouse_capture(), || {
+                            editor::edit(&path, &contents)
+                        })?;
+                        app.finish_external_edit(edited);
+                    }
                     last_blink = Instant::now();
                 }
                 Event::Paste(data) => {
@@ -78,7 +152,8 @@ async fn run_app(
                     last_blink = Instant::now();
                 }
                 Event::Resize(_, _) => {}
-                Event::FocusGained | Event::FocusLost | Event::Mouse(_) => {}
+                Event::Mouse(mouse) => app.on_mouse(mouse),
+                Event::FocusGained | Event::FocusLost => {}
             }
         }
     }
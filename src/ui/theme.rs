@@ -7,19 +7,127 @@ pub const VANILLA: Color = Color::Rgb(245, 238, 158);
 pub const SANDY_BROWN: Color = Color::Rgb(244, 158, 76);
 pub const FIRE_BRICK: Color = Color::Rgb(171, 52, 40);
 
-// UI Elements
-pub const PROMPT_BORDER: Color = SANDY_BROWN;
-pub const PROMPT_TEXT: Color = VANILLA;
+/// Colors for the TUI chrome, chosen with `/theme` or `runtime.theme`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub prompt_border: Color,
+    pub prompt_text: Color,
+    pub activity_border: Color,
+    pub banner_border: Color,
+    pub banner_text: Color,
+    pub banner_cat_ear: Color,
+    pub banner_cat_eye: Color,
+    pub banner_cat_mouth: Color,
+    pub banner_cat_whisker: Color,
+    pub status_text: Color,
+    /// Info and reasoning messages, and the plan steps.
+    pub info_text: Color,
+    /// Tool-call lines in the actions pane.
+    pub muted_text: Color,
+    /// Background of the plan pane.
+    pub plan_bg: Color,
+    /// Background of the proposed-edit and last-batch diff panes.
+    pub diff_bg: Color,
+    pub border_type: BorderType,
+}
 
-pub const ACTIVITY_BORDER: Color = SANDY_BROWN;
-pub const BANNER_BORDER: Color = SANDY_BROWN;
+pub const WARM_CAT: Theme = Theme {
+    name: "warm-cat",
+    prompt_border: SANDY_BROWN,
+    prompt_text: VANILLA,
+    activity_border: SANDY_BROWN,
+    banner_border: SANDY_BROWN,
+    banner_text: SANDY_BROWN,
+    banner_cat_ear: SANDY_BROWN,
+    banner_cat_eye: VANILLA,
+    banner_cat_mouth: FIRE_BRICK,
+    banner_cat_whisker: FIRE_BRICK,
+    status_text: VANILLA,
+    info_text: Color::Gray,
+    muted_text: Color::DarkGray,
+    plan_bg: Color::Black,
+    diff_bg: Color::Rgb(30, 30, 30),
+    border_type: BorderType::Thick,
+};
 
-pub const BANNER_TEXT: Color = SANDY_BROWN;
-pub const BANNER_CAT_EAR: Color = SANDY_BROWN;
-pub const BANNER_CAT_EYE: Color = VANILLA;
-pub const BANNER_CAT_MOUTH: Color = FIRE_BRICK;
-pub const BANNER_CAT_WHISKER: Color = FIRE_BRICK;
+/// Text in the terminal's own foreground and saturated ANSI borders, so it
+/// stays readable on light backgrounds too.
+pub const HIGH_CONTRAST: Theme = Theme {
+    name: "high-contrast",
+    prompt_border: Color::Blue,
+    prompt_text: Color::Reset,
+    activity_border: Color::Magenta,
+    banner_border: Color::Blue,
+    banner_text: Color::Reset,
+    banner_cat_ear: Color::Blue,
+    banner_cat_eye: Color::Reset,
+    banner_cat_mouth: Color::Red,
+    banner_cat_whisker: Color::Red,
+    status_text: Color::Reset,
+    info_text: Color::Reset,
+    muted_text: Color::Reset,
+    plan_bg: Color::Reset,
+    diff_bg: Color::Reset,
+    border_type: BorderType::Double,
+};
 
-pub const STATUS_TEXT: Color = VANILLA;
+pub const MONOCHROME: Theme = Theme {
+    name: "monochrome",
+    prompt_border: Color::Reset,
+    prompt_text: Color::Reset,
+    activity_border: Color::Reset,
+    banner_border: Color::Reset,
+    banner_text: Color::Reset,
+    banner_cat_ear: Color::Reset,
+    banner_cat_eye: Color::Reset,
+    banner_cat_mouth: Color::Reset,
+    banner_cat_whisker: Color::Reset,
+    status_text: Color::Reset,
+    info_text: Color::Reset,
+    muted_text: Color::Reset,
+    plan_bg: Color::Reset,
+    diff_bg: Color::Reset,
+    border_type: BorderType::Plain,
+};
 
-pub const UI_BORDER_TYPE: BorderType = BorderType::Thick;
+pub const THEMES: &[Theme] = &[WARM_CAT, HIGH_CONTRAST, MONOCHROME];
+
+impl Theme {
+    pub fn by_name(name: &str) -> Option<Theme> {
+        THEMES
+            .iter()
+            .find(|theme| theme.name.eq_ignore_ascii_case(name))
+            .copied()
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        WARM_CAT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_theme_resolves_to_warm_cat() {
+        let cfg = crate::config::AppConfig::default();
+        assert_eq!(Theme::by_name(&cfg.runtime.theme), Some(WARM_CAT));
+        assert_eq!(Theme::by_name("High-Contrast"), Some(HIGH_CONTRAST));
+        assert_eq!(Theme::by_name("solarized"), None);
+    }
+
+    #[test]
+    fn high_contrast_keeps_the_terminal_background() {
+        for name in ["high-contrast", "monochrome"] {
+            let theme = Theme::by_name(name).unwrap();
+            assert_eq!(theme.plan_bg, Color::Reset, "{name}");
+            assert_eq!(theme.diff_bg, Color::Reset, "{name}");
+            assert_eq!(theme.info_text, Color::Reset, "{name}");
+            assert_eq!(theme.muted_text, Color::Reset, "{name}");
+        }
+    }
+}
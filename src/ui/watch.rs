@@ -0,0 +1,156 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, channel},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{fsutil::TEMP_PREFIX, llm::SEARCH_SKIP_DIRS};
+
+/// Changes are only reported once the tree has been quiet this long, so a
+/// burst of saves or a `git checkout` shows up as a single update.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// How long events for a file Smol just wrote itself are ignored.
+const OWN_WRITE_GRACE: Duration = Duration::from_secs(2);
+
+/// Watches the repo for edits made outside Smol between turns. Paths under
+/// the same directories repo search skips, and the temp files Smol stages
+/// its writes in, are ignored.
+pub(crate) struct ContextWatcher {
+    _watcher: RecommendedWatcher,
+    rx: Receiver<PathBuf>,
+    repo_root: PathBuf,
+    changed: BTreeSet<PathBuf>,
+    last_event: Option<Instant>,
+    own_writes: BTreeMap<PathBuf, Instant>,
+}
+
+impl ContextWatcher {
+    pub(crate) fn start(repo_root: &Path) -> Result<Self> {
+        let (tx, rx) = channel();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let Ok(event) = res else {
+                return;
+            };
+            if matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            ) {
+                for path in event.paths {
+                    let _ = tx.send(path);
+                }
+            }
+        })
+        .context("failed to start file watcher")?;
+        watcher
+            .watch(repo_root, RecursiveMode::Recursive)
+            .with_context(|| format!("failed to watch {}", repo_root.display()))?;
+        Ok(Self {
+            _watcher: watcher,
+            rx,
+            repo_root: repo_root.to_path_buf(),
+            changed: BTreeSet::new(),
+            last_event: None,
+            own_writes: BTreeMap::new(),
+        })
+    }
+
+    /// Drains pending filesystem events.
+    pub(crate) fn poll(&mut self) {
+        self.own_writes
+            .retain(|_, at| at.elapsed() < OWN_WRITE_GRACE);
+        while let Ok(path) = self.rx.try_recv() {
+            let rel = self.relative(&path);
+            if is_ignored(&rel) || self.own_writes.contains_key(&rel) {
+                continue;
+            }
+            self.changed.insert(rel);
+            self.last_event = Some(Instant::now());
+        }
+    }
+
+    /// Records a write Smol made itself (apply, undo, redo) so it is not
+    /// reported as an outside change.
+    pub(crate) fn note_own_write(&mut self, path: &Path) {
+        let rel = self.relative(path);
+        self.own_writes.insert(rel, Instant::now());
+    }
+
+    /// Files changed since the last [`clear`](Self::clear), once events have
+    /// settled; `None` while a burst is still arriving.
+    pub(crate) fn stale_paths(&self) -> Option<&BTreeSet<PathBuf>> {
+        let settled = self.last_event.is_some_and(|t| t.elapsed() >= DEBOUNCE);
+        (settled && !self.changed.is_empty()).then_some(&self.changed)
+    }
+
+    /// Called when a turn starts, since each turn reads files afresh.
+    pub(crate) fn clear(&mut self) {
+        self.changed.clear();
+        self.last_event = None;
+    }
+
+    fn relative(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.repo_root)
+            .unwrap_or(path)
+            .to_path_buf()
+    }
+}
+
+fn is_ignored(rel: &Path) -> bool {
+    let staged = rel
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with(TEMP_PREFIX));
+    staged
+        || rel.components().any(|c| {
+            c.as_os_str()
+                .to_str()
+                .is_some_and(|name| SEARCH_SKIP_DIRS.contains(&name))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_paths_under_skipped_dirs() {
+        assert!(is_ignored(Path::new(".git/index")));
+        assert!(is_ignored(Path::new("target/debug/smol")));
+        assert!(is_ignored(Path::new(".smol/backups/1/a.rs")));
+        assert!(is_ignored(Path::new("src/.smol-tmpA1b2C3")));
+        assert!(!is_ignored(Path::new("src/main.rs")));
+    }
+
+    #[test]
+    fn own_writes_are_suppressed_and_outside_edits_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let backups = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("ours.rs"), "old").unwrap();
+        let mut watcher = ContextWatcher::start(&root).unwrap();
+
+        watcher.note_own_write(&root.join("ours.rs"));
+        crate::fsutil::backup_and_write(
+            &root.join("ours.rs"),
+            "new",
+            &backups.path().join("ours.rs"),
+        )
+        .unwrap();
+        std::fs::write(root.join("theirs.rs"), "edited").unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !watcher.changed.contains(Path::new("theirs.rs")) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+            watcher.poll();
+        }
+        assert_eq!(
+            watcher.changed.iter().collect::<Vec<_>>(),
+            [Path::new("theirs.rs")]
+        );
+    }
+}
@@ -0,0 +1,155 @@
+/// Cuts `s` to at most `max` bytes without splitting a UTF-8 character.
+pub fn truncate(s: &str, max: usize) -> String {
+    if s.len() <= max {
+        return s.to_string();
+    }
+
+    let mut end = 0;
+    for (idx, ch) in s.char_indices() {
+        let next = idx + ch.len_utf8();
+        if next > max {
+            break;
+        }
+        end = next;
+    }
+    s[..end].to_string()
+}
+
+/// Like [`truncate`], but a cut text ends with `[... truncated N of M bytes ...]`
+/// so the model and the user can tell it is incomplete.
+pub fn truncate_marked(s: &str, max: usize) -> String {
+    let kept = truncate(s, max);
+    if kept.len() == s.len() {
+        return kept;
+    }
+    format!(
+        "{kept}\n[... truncated {} of {} bytes ...]",
+        s.len() - kept.len(),
+        s.len()
+    )
+}
+
+/// The first `max` lines of `s` (at least one), and whether any lines were
+/// left out.
+pub fn first_lines(s: &str, max: usize) -> (&str, bool) {
+    match s.match_indices('\n').nth(max.saturating_sub(1)) {
+        Some((end, _)) if end + 1 < s.len() => (&s[..end], true),
+        _ => (s, false),
+    }
+}
+
+/// Standard, padded base64, for embedding files in `data:` URLs.
+pub fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// `/undo` undoes one edit; `/undo <n>` undoes the last `n` (at least 1).
+/// `None` for anything else after the command.
+pub fn parse_undo_count(cmd: &str) -> Option<usize> {
+    match cmd.split_whitespace().nth(1) {
+        None => Some(1),
+        Some(n) => n.parse().ok().filter(|&n| n > 0),
+    }
+}
+
+/// Whether output to stdout may carry ANSI styling: not with `--no-color`, a
+/// non-empty `NO_COLOR` (<https://no-color.org>), or when stdout is not a
+/// terminal.
+pub fn color_enabled(no_color_flag: bool) -> bool {
+    use std::io::IsTerminal;
+    wants_color(
+        no_color_flag,
+        std::env::var_os("NO_COLOR").as_deref(),
+        std::io::stdout().is_terminal(),
+    )
+}
+
+fn wants_color(no_color_flag: bool, no_color_env: Option<&std::ffi::OsStr>, tty: bool) -> bool {
+    !no_color_flag && no_color_env.is_none_or(|value| value.is_empty()) && tty
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_needs_a_terminal_and_no_opt_out() {
+        assert!(wants_color(false, None, true));
+        assert!(wants_color(false, Some("".as_ref()), true));
+        assert!(!wants_color(false, Some("1".as_ref()), true));
+        assert!(!wants_color(true, None, true));
+        assert!(!wants_color(false, None, false));
+    }
+
+    #[test]
+    fn undo_count_defaults_to_one_and_rejects_zero() {
+        assert_eq!(parse_undo_count("/undo"), Some(1));
+        assert_eq!(parse_undo_count("/undo  3"), Some(3));
+        assert_eq!(parse_undo_count("/undo 0"), None);
+        assert_eq!(parse_undo_count("/undo -2"), None);
+        assert_eq!(parse_undo_count("/undo all"), None);
+    }
+
+    #[test]
+    fn first_lines_reports_dropped_lines() {
+        assert_eq!(first_lines("a\nb\nc", 2), ("a\nb", true));
+        assert_eq!(first_lines("a\nb\n", 2), ("a\nb\n", false));
+        assert_eq!(first_lines("a", 5), ("a", false));
+    }
+
+    #[test]
+    fn base64_encode_pads_partial_chunks() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_encode(&[0xff, 0xfe]), "//4=");
+    }
+
+    #[test]
+    fn truncate_marked_reports_dropped_bytes() {
+        assert_eq!(truncate_marked("short", 10), "short");
+        assert_eq!(
+            truncate_marked("hello world", 5),
+            "hello\n[... truncated 6 of 11 bytes ...]"
+        );
+    }
+
+    #[test]
+    fn truncate_preserves_ascii_within_limit() {
+        assert_eq!(truncate("hello world", 5), "hello");
+    }
+
+    #[test]
+    fn truncate_respects_utf8_boundaries() {
+        let sample = "éèê"; // multibyte characters
+        assert_eq!(truncate(sample, 4), "éè");
+    }
+
+    #[test]
+    fn truncate_returns_empty_when_limit_too_small_for_char() {
+        assert_eq!(truncate("é", 1), "");
+    }
+
+    #[test]
+    fn truncate_does_not_split_char_straddling_limit() {
+        // The emoji occupies bytes 10..14, so an 11-byte cut lands inside it.
+        let readme = "# Project 🚀 launch";
+        assert_eq!(truncate(readme, 11), "# Project ");
+    }
+}